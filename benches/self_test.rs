@@ -0,0 +1,74 @@
+extern crate synacor_challenge;
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+use synacor_challenge::console::{Input, Output};
+use synacor_challenge::jit::Backend;
+use synacor_challenge::loader::{read_challenge_file, convert_to_u16_le};
+use synacor_challenge::vm::{VM, RunFailure};
+
+const RUNS : u32 = 5;
+
+struct Run
+{
+    duration : Duration,
+    vm : VM,
+    output : String,
+}
+
+// Runs the challenge until it first asks for input, which covers the
+// self-test.
+fn run_self_test(memory : &[u16], use_decode_cache : bool, backend : Backend) -> Run
+{
+    let mut vm = VM::new(memory.to_vec());
+    vm.set_input(Input::Queue(VecDeque::new()));
+    vm.set_output(Output::Buffer(String::new()));
+    vm.set_decode_cache_enabled(use_decode_cache);
+    vm.set_backend(backend);
+
+    let start = Instant::now();
+    match vm.run_steps(u64::MAX)
+    {
+        Err(RunFailure::InputNeeded) => (),
+        result => panic!("self-test failed: {:?}", result),
+    }
+    let duration = start.elapsed();
+    let output = vm.take_output();
+    Run { duration, vm, output }
+}
+
+fn best_of(memory : &[u16], use_decode_cache : bool, backend : Backend) -> Run
+{
+    (0..RUNS)
+        .map(|_| run_self_test(memory, use_decode_cache, backend))
+        .min_by_key(|run| run.duration)
+        .unwrap()
+}
+
+fn assert_same_state(expected : &Run, actual : &Run)
+{
+    assert_eq!(expected.vm.step_nb(), actual.vm.step_nb());
+    assert_eq!(expected.vm.program_counter(), actual.vm.program_counter());
+    assert_eq!(expected.vm.registers(), actual.vm.registers());
+    assert_eq!(expected.vm.stack(), actual.vm.stack());
+    assert!(expected.vm.memory() == actual.vm.memory());
+    assert_eq!(expected.output, actual.output);
+}
+
+fn main()
+{
+    let content = read_challenge_file("challenge.bin").expect("cannot read challenge.bin");
+    let memory = convert_to_u16_le(&content).expect("invalid challenge.bin");
+
+    let uncached = best_of(&memory, false, Backend::Interpreter);
+    let cached = best_of(&memory, true, Backend::Interpreter);
+    let jit = best_of(&memory, true, Backend::BlockJit);
+    assert_same_state(&uncached, &cached);
+    assert_same_state(&uncached, &jit);
+
+    let speedup = |run : &Run| uncached.duration.as_secs_f64() / run.duration.as_secs_f64();
+    println!("self-test: {} steps", uncached.vm.step_nb());
+    println!("without decode cache: {:?}", uncached.duration);
+    println!("with decode cache:    {:?} ({:.2}x)", cached.duration, speedup(&cached));
+    println!("block jit:            {:?} ({:.2}x)", jit.duration, speedup(&jit));
+}
@@ -0,0 +1,18 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use synacor_challenge::opcode::read_memory_to_op_code;
+
+// Decodes at every offset of an arbitrary word sequence, plus the last
+// offsets of the address space where operand offsets used to overflow.
+fuzz_target!(|data : &[u8]| {
+    let words : Vec<u16> = data.chunks_exact(2).map(|pair| u16::from_le_bytes([pair[0], pair[1]])).collect();
+    for offset in 0..=words.len()
+    {
+        let _ = read_memory_to_op_code(&words[..], offset as u16);
+    }
+    for offset in 0xFFFC..=0xFFFF
+    {
+        let _ = read_memory_to_op_code(&words[..], offset);
+    }
+});
@@ -0,0 +1,28 @@
+#![no_main]
+
+use std::collections::VecDeque;
+use libfuzzer_sys::fuzz_target;
+use synacor_challenge::conformance::Conformance;
+use synacor_challenge::console::{Input, Output};
+use synacor_challenge::jit::Backend;
+use synacor_challenge::run_until::StopConditions;
+use synacor_challenge::vm::VM;
+
+const MAX_STEPS : u64 = 100_000;
+
+// Runs an arbitrary program with a step budget under every backend and
+// conformance profile. Faults are fine, panics are not. The first byte picks
+// the configuration, the rest is the program in the format of challenge.bin.
+fuzz_target!(|data : &[u8]| {
+    let Some((&config, program)) = data.split_first() else { return; };
+    let words : Vec<u16> = program.chunks_exact(2).map(|pair| u16::from_le_bytes([pair[0], pair[1]])).collect();
+
+    let mut vm = VM::new(words);
+    vm.set_backend(if config & 1 == 0 { Backend::Interpreter } else { Backend::BlockJit });
+    vm.set_conformance(if config & 2 == 0 { Conformance::current() } else { Conformance::strict() });
+    vm.set_decode_cache_enabled(config & 4 == 0);
+    vm.set_input(Input::Queue(VecDeque::new()));
+    vm.set_output(Output::Buffer(String::new()));
+    vm.push_input_line("look");
+    vm.run(&StopConditions::new().max_steps(MAX_STEPS));
+});
@@ -0,0 +1,134 @@
+use std::collections::BTreeSet;
+use std::fmt::Write;
+
+// A `wmem` into a word that had been decoded as part of an instruction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Modification
+{
+    pub step : u64,
+    pub program_counter : u16,
+    pub address : u16,
+    pub old : u16,
+    pub new : u16,
+}
+
+// Consecutive modified words with the steps of the first and last write
+// and the instructions that wrote them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModifiedRange
+{
+    pub start : u16,
+    pub end : u16,
+    pub writes : usize,
+    pub first_step : u64,
+    pub last_step : u64,
+    pub writers : BTreeSet<u16>,
+}
+
+// Records which words were decoded as instructions, by the interpreter or
+// when translating blocks, and every later write to them. Code decoded
+// again after a write is still code, so each write to it is recorded.
+#[derive(Debug, Clone, Default)]
+pub struct CodeWatch
+{
+    decoded : Vec<bool>,
+    modifications : Vec<Modification>,
+}
+
+impl CodeWatch
+{
+    pub fn new() -> CodeWatch
+    {
+        CodeWatch::default()
+    }
+
+    pub fn on_decode(&mut self, address : u16, size : u16)
+    {
+        let end = address as usize + size as usize;
+        if end > self.decoded.len()
+        {
+            self.decoded.resize(end, false);
+        }
+        for decoded in &mut self.decoded[address as usize..end]
+        {
+            *decoded = true;
+        }
+    }
+
+    pub fn on_write(&mut self, step : u64, program_counter : u16, address : u16, old : u16, new : u16)
+    {
+        if self.is_decoded(address)
+        {
+            self.modifications.push(Modification { step, program_counter, address, old, new });
+        }
+    }
+
+    pub fn is_decoded(&self, address : u16) -> bool
+    {
+        self.decoded.get(address as usize).cloned().unwrap_or(false)
+    }
+
+    pub fn modifications(&self) -> &[Modification]
+    {
+        &self.modifications
+    }
+
+    pub fn modified_ranges(&self) -> Vec<ModifiedRange>
+    {
+        let mut modifications : Vec<&Modification> = self.modifications.iter().collect();
+        modifications.sort_by_key(|modification| (modification.address, modification.step));
+
+        let mut ranges : Vec<ModifiedRange> = vec!();
+        for modification in modifications
+        {
+            match ranges.last_mut()
+            {
+                Some(ref mut range) if modification.address <= range.end =>
+                {
+                    range.end = range.end.max(modification.address + 1);
+                    range.writes += 1;
+                    range.first_step = range.first_step.min(modification.step);
+                    range.last_step = range.last_step.max(modification.step);
+                    range.writers.insert(modification.program_counter);
+                },
+                _ =>
+                    ranges.push
+                    (
+                        ModifiedRange
+                        {
+                            start : modification.address,
+                            end : modification.address + 1,
+                            writes : 1,
+                            first_step : modification.step,
+                            last_step : modification.step,
+                            writers : [modification.program_counter].iter().cloned().collect(),
+                        }
+                    ),
+            }
+        }
+        ranges
+    }
+
+    pub fn report(&self) -> String
+    {
+        let ranges = self.modified_ranges();
+        let mut text = format!("{} writes to code in {} ranges\n", self.modifications.len(), ranges.len());
+        for range in ranges
+        {
+            let writers : Vec<String> = range.writers.iter().map(|writer| writer.to_string()).collect();
+            let _ =
+                writeln!
+                (
+                    text,
+                    "{}..{}: {} writes, steps {} to {}, written by {}",
+                    range.start,
+                    range.end,
+                    range.writes,
+                    range.first_step,
+                    range.last_step,
+                    writers.join(" ")
+                );
+        }
+        text
+    }
+}
@@ -0,0 +1,91 @@
+// How the VM handles the corners where the architecture spec and the
+// original implementation of this VM disagree. `current` keeps the historic
+// behavior and is the default, `strict` follows arch-spec to the letter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Conformance
+{
+    pub empty_return : EmptyReturn,
+    pub register_read : RegisterRead,
+    pub memory_range : MemoryRange,
+    pub jump_targets : JumpTargets,
+}
+
+// `ret` with nothing on the stack.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EmptyReturn
+{
+    // Fail with `RunFailure::CannotReturnStackIsEmpty`.
+    Fail,
+    // "ret: remove the top element from the stack and jump to it; empty
+    // stack = halt"
+    Halt,
+}
+
+// What a register may hold and how a register operand is read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegisterRead
+{
+    // Registers only hold numbers: `rmem` of a word that is not a number
+    // fails with `RunFailure::InvalidLiteral`, and a register holding a
+    // register reference is followed until a number is found, or fails with
+    // `RunFailure::RegisterLoop` when the references go round in a loop.
+    Chained,
+    // Registers hold any 16 bit word loaded by `rmem` or `pop`, and a
+    // register operand reads the register once.
+    Raw,
+}
+
+// Which addresses `rmem` and `wmem` accept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemoryRange
+{
+    // Only the words of the loaded image, other addresses fail with
+    // `RunFailure::InvalidAddress`.
+    Image,
+    // The whole 15 bit address space, the words after the image start at 0.
+    Full,
+}
+
+// What `jmp`, `jt` and `jf` accept as the address to jump to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JumpTargets
+{
+    // Only numbers, a register target fails like an undecodable instruction
+    // with `ReadOpCodeFailure::InvalidOperandValue`.
+    Literal,
+    // Numbers or registers, like every other operand.
+    Any,
+}
+
+impl Conformance
+{
+    pub fn current() -> Conformance
+    {
+        Conformance
+        {
+            empty_return : EmptyReturn::Fail,
+            register_read : RegisterRead::Chained,
+            memory_range : MemoryRange::Image,
+            jump_targets : JumpTargets::Literal,
+        }
+    }
+
+    pub fn strict() -> Conformance
+    {
+        Conformance
+        {
+            empty_return : EmptyReturn::Halt,
+            register_read : RegisterRead::Raw,
+            memory_range : MemoryRange::Full,
+            jump_targets : JumpTargets::Any,
+        }
+    }
+}
+
+impl Default for Conformance
+{
+    fn default() -> Conformance
+    {
+        Conformance::current()
+    }
+}
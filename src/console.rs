@@ -0,0 +1,94 @@
+use std::collections::VecDeque;
+use std::io;
+
+// Where the VM reads its input lines from. `Queue` is used when the VM is
+// driven by a tool: once the queue is empty the VM stops and asks for more.
+#[derive(Clone)]
+pub enum Input
+{
+    Stdin,
+    Queue(VecDeque<String>),
+}
+
+impl Input
+{
+    pub fn read_line(&mut self) -> Option<String>
+    {
+        match *self
+        {
+            Input::Stdin =>
+            {
+                let mut line = String::new();
+                io::stdin()
+                    .read_line(&mut line)
+                    .expect("Failed to read line");
+                Some(line)
+            },
+            Input::Queue(ref mut lines) => lines.pop_front(),
+        }
+    }
+
+    pub fn has_queued_line(&self) -> bool
+    {
+        match *self
+        {
+            Input::Stdin => false,
+            Input::Queue(ref lines) => !lines.is_empty(),
+        }
+    }
+
+    pub fn push_line(&mut self, line : &str)
+    {
+        match *self
+        {
+            Input::Stdin => (),
+            Input::Queue(ref mut lines) =>
+            {
+                let mut line = line.to_owned();
+                if !line.ends_with('\n')
+                {
+                    line.push('\n');
+                }
+                lines.push_back(line);
+            },
+        }
+    }
+}
+
+// Where the characters printed by `out` go.
+#[derive(Clone)]
+pub enum Output
+{
+    Stdout,
+    Buffer(String),
+}
+
+impl Output
+{
+    pub fn write_char(&mut self, c : char)
+    {
+        match *self
+        {
+            Output::Stdout => print!("{}", c),
+            Output::Buffer(ref mut buffer) => buffer.push(c),
+        }
+    }
+
+    pub fn write_str(&mut self, text : &str)
+    {
+        match *self
+        {
+            Output::Stdout => print!("{}", text),
+            Output::Buffer(ref mut buffer) => buffer.push_str(text),
+        }
+    }
+
+    pub fn take(&mut self) -> String
+    {
+        match *self
+        {
+            Output::Stdout => String::new(),
+            Output::Buffer(ref mut buffer) => ::std::mem::take(buffer),
+        }
+    }
+}
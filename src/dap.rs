@@ -0,0 +1,564 @@
+extern crate serde_json;
+
+use std::collections::{HashSet, VecDeque};
+use std::convert::TryFrom;
+use std::fs;
+use std::io;
+use std::io::{BufRead, Write};
+use std::sync::mpsc;
+use std::sync::mpsc::{Receiver, TryRecvError};
+use std::thread;
+use self::serde_json::{Value, json};
+use console::{Input, Output};
+use loader::{read_challenge_file, convert_to_u16_le};
+use run_until::{StopConditions, StopReason};
+use symbols::{Symbols, parse_address};
+use vm::VM;
+
+const THREAD_ID : u64 = 1;
+const REGISTERS_REFERENCE : u64 = 1;
+// Frame `n` shows its data slots under `STACK_REFERENCE + n`.
+const STACK_REFERENCE : u64 = 2;
+const CALL_OP_CODE : u16 = 17;
+// Steps run between two looks for a `pause` request.
+const RUN_SLICE : u64 = 100_000;
+// Larger messages are refused instead of allocated.
+const MAX_MESSAGE_LENGTH : usize = 4 * 1024 * 1024;
+
+// Debug Adapter Protocol server driving a VM, for editors.
+//
+// `launch` takes the `program` image, an optional `input` script whose
+// lines are fed to the program, an optional assembler `symbols` file and
+// `stopOnEntry`. Function breakpoints are labels or addresses, instruction
+// breakpoints are addresses. Once the input script is used up the program
+// pauses, and `evaluate` in the console sends the expression as the next
+// input line. Requests are read on their own thread, so `pause` stops a
+// program that runs without end.
+pub struct DapServer
+{
+    vm : Option<VM>,
+    symbols : Symbols,
+    function_breakpoints : HashSet<u16>,
+    instruction_breakpoints : HashSet<u16>,
+    stop_on_entry : bool,
+    seq : u64,
+    // Requests received while the program was running.
+    pending : VecDeque<Value>,
+}
+
+impl DapServer
+{
+    pub fn new() -> DapServer
+    {
+        DapServer
+        {
+            vm : None,
+            symbols : Symbols::new(),
+            function_breakpoints : HashSet::new(),
+            instruction_breakpoints : HashSet::new(),
+            stop_on_entry : false,
+            seq : 0,
+            pending : VecDeque::new(),
+        }
+    }
+
+    pub fn vm(&self) -> Option<&VM>
+    {
+        self.vm.as_ref()
+    }
+
+    // Answers requests from `input` until the client disconnects or closes
+    // the stream.
+    pub fn serve<R : BufRead + Send + 'static, W : Write>(&mut self, input : R, mut output : W) -> io::Result<()>
+    {
+        let requests = read_in_background(input);
+        loop
+        {
+            let request = match self.pending.pop_front()
+            {
+                Some(request) => request,
+                None =>
+                {
+                    match requests.recv()
+                    {
+                        Err(_) => return Ok(()),
+                        Ok(request) => request?,
+                    }
+                },
+            };
+            let messages = self.handle_request(&request, &requests);
+            for mut message in messages
+            {
+                self.seq += 1;
+                message["seq"] = json!(self.seq);
+                write_message(&mut output, &message)?;
+            }
+            output.flush()?;
+            if request["command"] == "disconnect"
+            {
+                return Ok(());
+            }
+        }
+    }
+
+    // The response to `request` followed by the events it triggers.
+    fn handle_request(&mut self, request : &Value, requests : &Receiver<io::Result<Value>>) -> Vec<Value>
+    {
+        let command = request["command"].as_str().unwrap_or("");
+        let arguments = &request["arguments"];
+        let mut events = vec!();
+        let result =
+            match command
+            {
+                "initialize" =>
+                {
+                    events.push(event("initialized", json!({})));
+                    Ok(capabilities())
+                },
+                "launch" => self.launch(arguments),
+                "setBreakpoints" => Ok(json!({ "breakpoints" : [] })),
+                "setFunctionBreakpoints" => Ok(self.set_function_breakpoints(arguments)),
+                "setInstructionBreakpoints" => Ok(self.set_instruction_breakpoints(arguments)),
+                "setExceptionBreakpoints" => Ok(json!({})),
+                "configurationDone" =>
+                {
+                    if self.stop_on_entry
+                    {
+                        events.push(stopped_event("entry", None));
+                    }
+                    else
+                    {
+                        events = self.resume(StopConditions::new(), "breakpoint", requests);
+                    }
+                    Ok(json!({}))
+                },
+                "threads" => Ok(json!({ "threads" : [{ "id" : THREAD_ID, "name" : "vm" }] })),
+                "continue" =>
+                {
+                    events = self.resume(StopConditions::new(), "breakpoint", requests);
+                    Ok(json!({ "allThreadsContinued" : true }))
+                },
+                "next" =>
+                {
+                    let conditions = self.step_over();
+                    events = self.resume(conditions, "step", requests);
+                    Ok(json!({}))
+                },
+                "stepIn" =>
+                {
+                    events = self.resume(StopConditions::new().max_steps(1), "step", requests);
+                    Ok(json!({}))
+                },
+                "stepOut" =>
+                {
+                    let conditions = self.step_out();
+                    events = self.resume(conditions, "step", requests);
+                    Ok(json!({}))
+                },
+                // The program only runs while a request is being handled,
+                // a pause that stopped it is answered once it stopped.
+                "pause" => Ok(json!({})),
+                "stackTrace" => self.stack_trace(),
+                "scopes" =>
+                {
+                    let frame = arguments["frameId"].as_u64().unwrap_or(0);
+                    Ok
+                    (
+                        json!
+                        ({
+                            "scopes" :
+                            [
+                                { "name" : "Registers", "variablesReference" : REGISTERS_REFERENCE, "expensive" : false },
+                                { "name" : "Stack", "variablesReference" : STACK_REFERENCE + frame, "expensive" : false },
+                            ]
+                        })
+                    )
+                },
+                "variables" => self.variables(arguments),
+                "evaluate" => self.evaluate(arguments),
+                "disconnect" => Ok(json!({})),
+                _ => Err(format!("unsupported request {}", command)),
+            };
+
+        let mut messages = vec!(response(request, result));
+        messages.extend(events);
+        messages
+    }
+
+    fn launch(&mut self, arguments : &Value) -> Result<Value, String>
+    {
+        let program = arguments["program"].as_str().ok_or("missing program")?;
+        let content = read_challenge_file(program).map_err(|e| format!("cannot read {}: {}", program, e))?;
+        let memory = convert_to_u16_le(&content).map_err(|e| format!("invalid program {}: {:?}", program, e))?;
+
+        let mut lines = VecDeque::new();
+        if let Some(script) = arguments["input"].as_str()
+        {
+            let text = fs::read_to_string(script).map_err(|e| format!("cannot read {}: {}", script, e))?;
+            lines.extend(text.lines().map(|line| format!("{}\n", line)));
+        }
+        if let Some(file_name) = arguments["symbols"].as_str()
+        {
+            self.symbols = Symbols::load(file_name).map_err(|e| format!("cannot load {}: {:?}", file_name, e))?;
+        }
+
+        let mut vm = VM::new(memory);
+        vm.set_input(Input::Queue(lines));
+        vm.set_output(Output::Buffer(String::new()));
+        self.vm = Some(vm);
+        self.stop_on_entry = arguments["stopOnEntry"].as_bool().unwrap_or(false);
+        Ok(json!({}))
+    }
+
+    fn set_function_breakpoints(&mut self, arguments : &Value) -> Value
+    {
+        let names : Vec<&str> = breakpoint_list(arguments).iter().map(|breakpoint| breakpoint["name"].as_str().unwrap_or("")).collect();
+        let addresses : Vec<Option<u16>> = names.iter().map(|name| self.symbols.resolve(name)).collect();
+        self.function_breakpoints = addresses.iter().filter_map(|&address| address).collect();
+        json!({ "breakpoints" : addresses.iter().map(|&address| breakpoint(address)).collect::<Vec<Value>>() })
+    }
+
+    fn set_instruction_breakpoints(&mut self, arguments : &Value) -> Value
+    {
+        let addresses : Vec<Option<u16>> =
+            breakpoint_list(arguments)
+                .iter()
+                .map
+                (
+                    |breakpoint|
+                    {
+                        let address = parse_address(breakpoint["instructionReference"].as_str()?)?;
+                        let offset = breakpoint["offset"].as_i64().unwrap_or(0);
+                        u16::try_from(address as i64 + offset).ok()
+                    }
+                )
+                .collect();
+        self.instruction_breakpoints = addresses.iter().filter_map(|&address| address).collect();
+        json!({ "breakpoints" : addresses.iter().map(|&address| breakpoint(address)).collect::<Vec<Value>>() })
+    }
+
+    // Steps over calls: a call runs until it returns to the next
+    // instruction with the stack back to its current depth.
+    fn step_over(&self) -> StopConditions
+    {
+        let vm = match self.vm
+        {
+            Some(ref vm) => vm,
+            None => return StopConditions::new(),
+        };
+        let program_counter = vm.program_counter();
+        if vm.memory().get(program_counter as usize) != Some(CALL_OP_CODE)
+        {
+            return StopConditions::new().max_steps(1);
+        }
+        let return_address = program_counter.wrapping_add(2);
+        let depth = vm.stack().len();
+        StopConditions::new().when(move |vm| vm.program_counter() == return_address && vm.stack().len() == depth)
+    }
+
+    // Runs until the innermost frame returns.
+    fn step_out(&self) -> StopConditions
+    {
+        let frame = self.vm.as_ref().and_then(|vm| vm.backtrace().into_iter().next());
+        match frame.and_then(|frame| Some((frame.call?, frame.base - 1)))
+        {
+            Some((call, depth)) =>
+            {
+                let return_address = call.caller.wrapping_add(2);
+                StopConditions::new().when(move |vm| vm.program_counter() == return_address && vm.stack().len() == depth)
+            },
+            None => StopConditions::new().max_steps(1),
+        }
+    }
+
+    // Runs with the breakpoints added to `conditions`, forwards the output
+    // and reports why the program stopped. The program runs in slices of
+    // `RUN_SLICE` steps and stops early on a `pause` or `disconnect`.
+    fn resume(&mut self, conditions : StopConditions, reason : &str, requests : &Receiver<io::Result<Value>>) -> Vec<Value>
+    {
+        let vm = match self.vm
+        {
+            Some(ref mut vm) => vm,
+            None => return vec!(),
+        };
+        let mut conditions =
+            self.function_breakpoints
+                .iter()
+                .chain(self.instruction_breakpoints.iter())
+                .fold(conditions.stop_on_input(), |conditions, &address| conditions.stop_at(address));
+        let last_step = conditions.max_steps.map(|max_steps| vm.step_nb().saturating_add(max_steps));
+        let stop = loop
+        {
+            let remaining = last_step.map_or(RUN_SLICE, |last_step| last_step - vm.step_nb());
+            conditions.max_steps = Some(remaining.min(RUN_SLICE));
+            match vm.run(&conditions)
+            {
+                StopReason::StepBudgetExhausted if last_step != Some(vm.step_nb()) =>
+                {
+                    if is_paused(requests, &mut self.pending)
+                    {
+                        break None;
+                    }
+                },
+                stop => break Some(stop),
+            }
+        };
+
+        let mut events = vec!();
+        let text = vm.take_output();
+        if !text.is_empty()
+        {
+            events.push(event("output", json!({ "category" : "stdout", "output" : text })));
+        }
+        let stop = match stop
+        {
+            Some(stop) => stop,
+            None =>
+            {
+                events.push(stopped_event("pause", None));
+                return events;
+            },
+        };
+        match stop
+        {
+            StopReason::Halted =>
+            {
+                events.push(event("exited", json!({ "exitCode" : 0 })));
+                events.push(event("terminated", json!({})));
+            },
+            StopReason::InputNeeded => events.push(stopped_event("pause", Some("input needed".to_owned()))),
+            StopReason::Fault(failure) => events.push(stopped_event("exception", Some(format!("{:?}", failure)))),
+            StopReason::PcReached(_) => events.push(stopped_event("breakpoint", None)),
+            _ => events.push(stopped_event(reason, None)),
+        }
+        events
+    }
+
+    fn stack_trace(&self) -> Result<Value, String>
+    {
+        let vm = self.vm.as_ref().ok_or("not launched")?;
+        let mut addresses = vec!(vm.program_counter());
+        addresses.extend(vm.backtrace().iter().filter_map(|frame| frame.call).map(|call| call.caller));
+        let frames : Vec<Value> =
+            addresses
+                .iter()
+                .enumerate()
+                .map
+                (
+                    |(id, &address)|
+                        json!
+                        ({
+                            "id" : id,
+                            "name" : self.symbols.describe(address),
+                            "line" : 0,
+                            "column" : 0,
+                            "instructionPointerReference" : format!("0x{:04x}", address),
+                        })
+                )
+                .collect();
+        Ok(json!({ "stackFrames" : frames, "totalFrames" : frames.len() }))
+    }
+
+    fn variables(&self, arguments : &Value) -> Result<Value, String>
+    {
+        let vm = self.vm.as_ref().ok_or("not launched")?;
+        let variables : Vec<Value> =
+            match arguments["variablesReference"].as_u64()
+            {
+                Some(REGISTERS_REFERENCE) =>
+                {
+                    let mut variables : Vec<Value> =
+                        vm.registers().iter().enumerate().map(|(r, &value)| variable(&format!("r{}", r), value)).collect();
+                    variables.push(variable("pc", vm.program_counter()));
+                    variables
+                },
+                Some(reference) if reference >= STACK_REFERENCE =>
+                {
+                    let backtrace = vm.backtrace();
+                    let frame = backtrace.get((reference - STACK_REFERENCE) as usize).ok_or("unknown frame")?;
+                    frame.data
+                        .iter()
+                        .enumerate()
+                        .rev()
+                        .map(|(i, &value)| variable(&format!("[{}]", frame.base + i), value))
+                        .collect()
+                },
+                _ => return Err("unknown variables reference".to_owned()),
+            };
+        Ok(json!({ "variables" : variables }))
+    }
+
+    fn evaluate(&mut self, arguments : &Value) -> Result<Value, String>
+    {
+        let vm = self.vm.as_mut().ok_or("not launched")?;
+        let expression = arguments["expression"].as_str().unwrap_or("");
+        vm.push_input_line(expression);
+        Ok(json!({ "result" : format!("queued input: {}", expression), "variablesReference" : 0 }))
+    }
+}
+
+impl Default for DapServer
+{
+    fn default() -> DapServer
+    {
+        DapServer::new()
+    }
+}
+
+fn capabilities() -> Value
+{
+    json!
+    ({
+        "supportsConfigurationDoneRequest" : true,
+        "supportsFunctionBreakpoints" : true,
+        "supportsInstructionBreakpoints" : true,
+        "supportsSteppingGranularity" : false,
+    })
+}
+
+fn response(request : &Value, result : Result<Value, String>) -> Value
+{
+    let mut response =
+        json!
+        ({
+            "type" : "response",
+            "request_seq" : request["seq"],
+            "command" : request["command"],
+            "success" : result.is_ok(),
+        });
+    match result
+    {
+        Ok(body) => response["body"] = body,
+        Err(message) => response["message"] = json!(message),
+    }
+    response
+}
+
+fn event(name : &str, body : Value) -> Value
+{
+    json!({ "type" : "event", "event" : name, "body" : body })
+}
+
+fn stopped_event(reason : &str, text : Option<String>) -> Value
+{
+    let mut body = json!({ "reason" : reason, "threadId" : THREAD_ID, "allThreadsStopped" : true });
+    if let Some(text) = text
+    {
+        body["text"] = json!(text);
+    }
+    event("stopped", body)
+}
+
+fn breakpoint_list(arguments : &Value) -> &[Value]
+{
+    arguments["breakpoints"].as_array().map_or(&[], |breakpoints| breakpoints.as_slice())
+}
+
+fn breakpoint(address : Option<u16>) -> Value
+{
+    match address
+    {
+        Some(address) => json!({ "verified" : true, "instructionReference" : format!("0x{:04x}", address) }),
+        None => json!({ "verified" : false, "message" : "unknown label or address" }),
+    }
+}
+
+fn variable(name : &str, value : u16) -> Value
+{
+    json!({ "name" : name, "value" : format!("{} (0x{:04x})", value, value), "variablesReference" : 0 })
+}
+
+// Sends the messages read from `input` over a channel, which is closed at
+// the end of the stream or after the first error.
+fn read_in_background<R : BufRead + Send + 'static>(mut input : R) -> Receiver<io::Result<Value>>
+{
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn
+    (
+        move ||
+        {
+            loop
+            {
+                let message = match read_message(&mut input)
+                {
+                    Ok(None) => break,
+                    Ok(Some(message)) => Ok(message),
+                    Err(e) => Err(e),
+                };
+                let is_error = message.is_err();
+                if sender.send(message).is_err() || is_error
+                {
+                    break;
+                }
+            }
+        }
+    );
+    receiver
+}
+
+// Keeps the requests that came in while the program ran for later. True
+// when one of them asks to stop it, or the client went away.
+fn is_paused(requests : &Receiver<io::Result<Value>>, pending : &mut VecDeque<Value>) -> bool
+{
+    loop
+    {
+        match requests.try_recv()
+        {
+            Ok(Ok(request)) =>
+            {
+                let is_stop = request["command"] == "pause" || request["command"] == "disconnect";
+                pending.push_back(request);
+                if is_stop
+                {
+                    return true;
+                }
+            },
+            Ok(Err(_)) | Err(TryRecvError::Disconnected) => return true,
+            Err(TryRecvError::Empty) => return false,
+        }
+    }
+}
+
+// Reads the next `Content-Length` framed message. Returns None once the
+// stream is closed.
+pub fn read_message<R : BufRead>(input : &mut R) -> io::Result<Option<Value>>
+{
+    let mut length = None;
+    loop
+    {
+        let mut header = String::new();
+        if input.read_line(&mut header)? == 0
+        {
+            return Ok(None);
+        }
+        let header = header.trim();
+        if header.is_empty()
+        {
+            if length.is_some()
+            {
+                break;
+            }
+            continue;
+        }
+        if let Some(value) = header.strip_prefix("Content-Length:")
+        {
+            length = value.trim().parse::<usize>().ok();
+        }
+    }
+
+    let length = length.unwrap_or(0);
+    if length > MAX_MESSAGE_LENGTH
+    {
+        let error = format!("message of {} bytes is longer than {}", length, MAX_MESSAGE_LENGTH);
+        return Err(io::Error::new(io::ErrorKind::InvalidData, error));
+    }
+    let mut body = vec!(0; length);
+    input.read_exact(&mut body)?;
+    serde_json::from_slice(&body).map(Some).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+pub fn write_message<W : Write>(output : &mut W, message : &Value) -> io::Result<()>
+{
+    let body = message.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body)
+}
@@ -0,0 +1,72 @@
+use opcode::OpCode;
+
+// Longest instruction is an opcode followed by three operands.
+const MAX_INSTRUCTION_SIZE : usize = 4;
+
+// Decoded instructions keyed by their address. Writing to memory must call
+// `invalidate` so that any instruction overlapping the written word gets
+// decoded again.
+pub struct DecodeCache
+{
+    entries : Vec<Option<OpCode>>,
+}
+
+impl DecodeCache
+{
+    pub fn new() -> DecodeCache
+    {
+        DecodeCache { entries : vec!() }
+    }
+
+    pub fn get(&self, address : u16) -> Option<OpCode>
+    {
+        self.entries.get(address as usize).and_then(|entry| *entry)
+    }
+
+    pub fn insert(&mut self, address : u16, op_code : OpCode)
+    {
+        let address = address as usize;
+        if address >= self.entries.len()
+        {
+            self.entries.resize(address + 1, None);
+        }
+        self.entries[address] = Some(op_code);
+    }
+
+    pub fn invalidate(&mut self, address : u16)
+    {
+        let address = address as usize;
+        let first = address.saturating_sub(MAX_INSTRUCTION_SIZE - 1);
+        let end = (address + 1).min(self.entries.len());
+        if first < end
+        {
+            for entry in &mut self.entries[first..end]
+            {
+                *entry = None;
+            }
+        }
+    }
+
+    pub fn clear(&mut self)
+    {
+        self.entries.clear();
+    }
+}
+
+impl Default for DecodeCache
+{
+    fn default() -> DecodeCache
+    {
+        DecodeCache::new()
+    }
+}
+
+// A clone starts with an empty cache and refills it as it runs, which keeps
+// forking a VM cheap.
+impl Clone for DecodeCache
+{
+    fn clone(&self) -> DecodeCache
+    {
+        DecodeCache::new()
+    }
+}
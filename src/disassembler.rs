@@ -0,0 +1,76 @@
+use opcode::{ParsedNumber, WordSource, check_number};
+
+// Mnemonics and operand counts indexed by op code.
+const INSTRUCTIONS : [(&str, u16); 22] =
+[
+    ("halt", 0), ("set", 2), ("push", 1), ("pop", 1), ("eq", 3), ("gt", 3),
+    ("jmp", 1), ("jt", 2), ("jf", 2), ("add", 3), ("mult", 3), ("mod", 3),
+    ("and", 3), ("or", 3), ("not", 2), ("rmem", 2), ("wmem", 2), ("call", 1),
+    ("ret", 0), ("out", 1), ("in", 1), ("noop", 0),
+];
+
+// One decoded line of a listing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Line
+{
+    pub address : u16,
+    pub size : u16,
+    pub text : String,
+}
+
+// Disassembles the instruction at `address`. Words that are not op codes
+// are shown as data, one word at a time, and operands missing at the end
+// of memory as `?`. Unlike the decoder this never fails so it can be used
+// on any address, e.g. around the program counter.
+pub fn disassemble<M : WordSource + ?Sized>(memory : &M, address : u16) -> Line
+{
+    let word = match memory.word(address as usize)
+    {
+        Some(word) => word,
+        None => return Line { address, size : 1, text : "?".to_owned() },
+    };
+    let (name, operand_count) = match INSTRUCTIONS.get(word as usize)
+    {
+        Some(&instruction) => instruction,
+        None => return Line { address, size : 1, text : format!(".word {}", word) },
+    };
+
+    let mut text = name.to_owned();
+    for i in 1..=operand_count
+    {
+        text.push(' ');
+        match memory.word(address as usize + i as usize)
+        {
+            None => text.push('?'),
+            Some(operand) => text.push_str(&operand_text(name, operand)),
+        }
+    }
+    Line { address, size : 1 + operand_count, text }
+}
+
+// `count` lines starting at `address`.
+pub fn listing<M : WordSource + ?Sized>(memory : &M, address : u16, count : usize) -> Vec<Line>
+{
+    let mut lines = vec!();
+    let mut address = address;
+    for _ in 0..count
+    {
+        let line = disassemble(memory, address);
+        address = address.wrapping_add(line.size);
+        lines.push(line);
+    }
+    lines
+}
+
+fn operand_text(name : &str, operand : u16) -> String
+{
+    match check_number(operand)
+    {
+        ParsedNumber::Register(r) => format!("r{}", r),
+        ParsedNumber::InvalidNumber => format!("<{}>", operand),
+        ParsedNumber::LiteralValue(value) if name == "out" && (0x20..0x7f).contains(&value) =>
+            format!("'{}'", value as u8 as char),
+        ParsedNumber::LiteralValue(value) if name == "out" && value == 10 => "'\\n'".to_owned(),
+        ParsedNumber::LiteralValue(value) => value.to_string(),
+    }
+}
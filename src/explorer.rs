@@ -0,0 +1,429 @@
+use std::collections::HashMap;
+use std::collections::VecDeque;
+use console::{Input, Output};
+use run_until::{StopConditions, StopReason};
+use vm::{VM, RunFailure};
+
+// The game copies every typed line into a buffer in memory, so two visits
+// of the same room would not hash the same. Typing a nonsense command longer
+// than any real one overwrites that buffer identically every time.
+const NORMALIZE_COMMAND : &str = "xxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxx";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RoomView
+{
+    pub name : String,
+    pub description : String,
+    pub items : Vec<String>,
+    pub exits : Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Room
+{
+    pub id : usize,
+    pub name : String,
+    pub description : String,
+    pub fingerprint : u64,
+    pub items : Vec<String>,
+    pub exits : Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Passage
+{
+    pub from : usize,
+    pub direction : String,
+    pub to : usize,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct WorldGraph
+{
+    pub rooms : Vec<Room>,
+    pub passages : Vec<Passage>,
+    pub fatal_exits : Vec<(usize, String)>,
+}
+
+#[derive(Debug)]
+pub enum ExploreFailure
+{
+    NoRoomFound,
+    CommandFailed(RunFailure),
+    OutOfSteps,
+}
+
+#[derive(PartialEq)]
+enum RoomSection
+{
+    Description,
+    Items,
+    Exits,
+    Other,
+}
+
+enum CommandOutcome
+{
+    Prompt(String),
+    Stopped(RunFailure),
+    OutOfSteps,
+}
+
+pub struct Explorer
+{
+    pub max_steps_per_command : u64,
+    pub max_rooms : usize,
+}
+
+impl Default for Explorer
+{
+    fn default() -> Explorer
+    {
+        Explorer::new()
+    }
+}
+
+impl Explorer
+{
+    pub fn new() -> Explorer
+    {
+        Explorer
+        {
+            max_steps_per_command : 10_000_000,
+            max_rooms : 1000,
+        }
+    }
+
+    // Runs a breadth first search over the rooms reachable from `vm` by
+    // only walking through exits. `vm` must be waiting for a new input line.
+    pub fn explore(&self, vm : &VM) -> Result<WorldGraph, ExploreFailure>
+    {
+        let (mut start, view) = self.look(vm)?;
+
+        let mut graph = WorldGraph::default();
+        let mut known_rooms : HashMap<(String, String, u64), usize> = HashMap::new();
+        let mut to_visit : VecDeque<(usize, VM)> = VecDeque::new();
+
+        let fingerprint = self.fingerprint(&mut start);
+        let id = add_room(&mut graph, &mut known_rooms, view, fingerprint);
+        to_visit.push_back((id, start));
+
+        while let Some((id, state)) = to_visit.pop_front()
+        {
+            for exit in graph.rooms[id].exits.clone()
+            {
+                let mut next = state.clone();
+                match self.run_command(&mut next, &exit)
+                {
+                    CommandOutcome::Prompt(output) =>
+                    {
+                        if let Some(view) = parse_room(&output)
+                        {
+                            let fingerprint = self.fingerprint(&mut next);
+                            let key = (view.name.clone(), view.description.clone(), fingerprint);
+                            let to = match known_rooms.get(&key)
+                            {
+                                Some(&to) => to,
+                                None =>
+                                {
+                                    if graph.rooms.len() >= self.max_rooms
+                                    {
+                                        continue;
+                                    }
+                                    let to = add_room(&mut graph, &mut known_rooms, view, fingerprint);
+                                    to_visit.push_back((to, next));
+                                    to
+                                },
+                            };
+                            graph.passages.push(Passage { from : id, direction : exit, to });
+                        }
+                    },
+                    CommandOutcome::Stopped(RunFailure::Halt) =>
+                    {
+                        graph.fatal_exits.push((id, exit));
+                    },
+                    _ => (),
+                }
+            }
+        }
+        Ok(graph)
+    }
+
+    // Returns the room `vm` is in with its fingerprint, `vm` is untouched.
+    pub fn identify(&self, vm : &VM) -> Result<(RoomView, u64), ExploreFailure>
+    {
+        let (mut state, view) = self.look(vm)?;
+        let fingerprint = self.fingerprint(&mut state);
+        Ok((view, fingerprint))
+    }
+
+    fn look(&self, vm : &VM) -> Result<(VM, RoomView), ExploreFailure>
+    {
+        let mut state = vm.fork();
+        state.set_input(Input::Queue(VecDeque::new()));
+        state.set_output(Output::Buffer(String::new()));
+
+        let output = match self.run_command(&mut state, "look")
+        {
+            CommandOutcome::Prompt(output) => output,
+            CommandOutcome::Stopped(e) => return Err(ExploreFailure::CommandFailed(e)),
+            CommandOutcome::OutOfSteps => return Err(ExploreFailure::OutOfSteps),
+        };
+        match parse_room(&output)
+        {
+            None => Err(ExploreFailure::NoRoomFound),
+            Some(view) => Ok((state, view)),
+        }
+    }
+
+    // Hash of the whole memory once the input buffer has been normalized,
+    // `vm` is left waiting for input in the same room.
+    pub fn fingerprint(&self, vm : &mut VM) -> u64
+    {
+        self.run_command(vm, NORMALIZE_COMMAND);
+        vm.memory().state_hash()
+    }
+
+    fn run_command(&self, vm : &mut VM, command : &str) -> CommandOutcome
+    {
+        vm.take_output();
+        vm.push_input_line(command);
+        match vm.run(&StopConditions::new().max_steps(self.max_steps_per_command))
+        {
+            StopReason::InputNeeded => CommandOutcome::Prompt(vm.take_output()),
+            StopReason::Halted => CommandOutcome::Stopped(RunFailure::Halt),
+            StopReason::Fault(e) => CommandOutcome::Stopped(e),
+            _ => CommandOutcome::OutOfSteps,
+        }
+    }
+}
+
+fn add_room
+(
+    graph : &mut WorldGraph,
+    known_rooms : &mut HashMap<(String, String, u64), usize>,
+    view : RoomView,
+    fingerprint : u64
+) -> usize
+{
+    let id = graph.rooms.len();
+    known_rooms.insert((view.name.clone(), view.description.clone(), fingerprint), id);
+    graph.rooms.push
+    (
+        Room
+        {
+            id,
+            name : view.name,
+            description : view.description,
+            fingerprint,
+            items : view.items,
+            exits : view.exits,
+        }
+    );
+    id
+}
+
+// Parses the last room printed in `output`, which looks like:
+//
+// == Foothills ==
+// You find yourself standing at the base of an enormous mountain.
+//
+// Things of interest here:
+// - tablet
+//
+// There are 2 exits:
+// - doorway
+// - south
+pub fn parse_room(output : &str) -> Option<RoomView>
+{
+    let start = match output.rfind("\n== ")
+    {
+        Some(i) => i + 1,
+        None if output.starts_with("== ") => 0,
+        None => return None,
+    };
+    let mut lines = output[start..].lines();
+
+    let header = lines.next()?;
+    let name = header.trim_start_matches("== ").trim_end_matches(" ==").to_owned();
+
+    let mut description = String::new();
+    let mut items = vec!();
+    let mut exits = vec!();
+    let mut section = RoomSection::Description;
+
+    for line in lines
+    {
+        if line.starts_with("Things of interest here")
+        {
+            section = RoomSection::Items;
+        }
+        else if line.starts_with("There are") || line.starts_with("There is")
+        {
+            section = RoomSection::Exits;
+        }
+        else if line.starts_with("What do you do?")
+        {
+            break;
+        }
+        else if let Some(entry) = line.strip_prefix("- ")
+        {
+            let entry = entry.trim().to_owned();
+            match section
+            {
+                RoomSection::Items => items.push(entry),
+                RoomSection::Exits => exits.push(entry),
+                _ => (),
+            }
+        }
+        else if line.trim().is_empty()
+        {
+            if section != RoomSection::Description || !description.is_empty()
+            {
+                section = RoomSection::Other;
+            }
+        }
+        else if section == RoomSection::Description
+        {
+            if !description.is_empty()
+            {
+                description.push(' ');
+            }
+            description.push_str(line.trim());
+        }
+    }
+
+    Some(RoomView { name, description, items, exits })
+}
+
+impl WorldGraph
+{
+    pub fn find_room(&self, view : &RoomView, fingerprint : u64) -> Option<usize>
+    {
+        self.rooms.iter()
+            .find
+            (
+                |room|
+                room.fingerprint == fingerprint &&
+                    room.name == view.name &&
+                    room.description == view.description
+            )
+            .map(|room| room.id)
+    }
+
+    pub fn to_dot(&self) -> String
+    {
+        let mut dot = String::from("digraph world {\n");
+        for room in &self.rooms
+        {
+            dot.push_str(&format!("    r{} [label=\"{}\"];\n", room.id, escape(&room.name)));
+        }
+        for passage in &self.passages
+        {
+            dot.push_str
+            (
+                &format!
+                (
+                    "    r{} -> r{} [label=\"{}\"];\n",
+                    passage.from,
+                    passage.to,
+                    escape(&passage.direction)
+                )
+            );
+        }
+        for &(from, ref direction) in &self.fatal_exits
+        {
+            dot.push_str(&format!("    dead{}_{} [label=\"death\", shape=box];\n", from, escape_id(direction)));
+            dot.push_str
+            (
+                &format!
+                (
+                    "    r{} -> dead{}_{} [label=\"{}\"];\n",
+                    from,
+                    from,
+                    escape_id(direction),
+                    escape(direction)
+                )
+            );
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    pub fn to_json(&self) -> String
+    {
+        let rooms : Vec<String> =
+            self.rooms.iter().map
+            (
+                |room|
+                format!
+                (
+                    "{{\"id\":{},\"name\":\"{}\",\"description\":\"{}\",\"fingerprint\":{},\"items\":{},\"exits\":{}}}",
+                    room.id,
+                    escape(&room.name),
+                    escape(&room.description),
+                    room.fingerprint,
+                    json_string_list(&room.items),
+                    json_string_list(&room.exits)
+                )
+            ).collect();
+
+        let passages : Vec<String> =
+            self.passages.iter().map
+            (
+                |passage|
+                format!
+                (
+                    "{{\"from\":{},\"direction\":\"{}\",\"to\":{}}}",
+                    passage.from,
+                    escape(&passage.direction),
+                    passage.to
+                )
+            ).collect();
+
+        let fatal_exits : Vec<String> =
+            self.fatal_exits.iter().map
+            (
+                |&(from, ref direction)|
+                format!("{{\"from\":{},\"direction\":\"{}\"}}", from, escape(direction))
+            ).collect();
+
+        format!
+        (
+            "{{\"rooms\":[{}],\"passages\":[{}],\"fatal_exits\":[{}]}}\n",
+            rooms.join(","),
+            passages.join(","),
+            fatal_exits.join(",")
+        )
+    }
+}
+
+fn json_string_list(list : &[String]) -> String
+{
+    let quoted : Vec<String> = list.iter().map(|s| format!("\"{}\"", escape(s))).collect();
+    format!("[{}]", quoted.join(","))
+}
+
+// Escapes a string for a double quoted DOT label or JSON string.
+pub fn escape(s : &str) -> String
+{
+    let mut escaped = String::new();
+    for c in s.chars()
+    {
+        match c
+        {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\t' => escaped.push_str("\\t"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn escape_id(s : &str) -> String
+{
+    s.chars().map(|c| if c.is_ascii_alphanumeric() { c } else { '_' }).collect()
+}
@@ -0,0 +1,220 @@
+use memory::Memory;
+
+// Item locations that are not rooms.
+const IN_INVENTORY : u16 = 0;
+const NOWHERE : u16 = 32767;
+
+const ITEM_RECORD_SIZE : usize = 4;
+const MIN_ITEM_COUNT : usize = 4;
+const MAX_STRING_LENGTH : u16 = 8000;
+
+// Where the game keeps its state in memory. Items are 4-word records
+// `name, description, location, use routine` stored one after the other,
+// rooms are records starting with `name, description, exit names` and the
+// current room is a pointer to its record stored right after the items.
+// Strings are only readable once the program has decrypted itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GameLayout
+{
+    pub item_table : u16,
+    pub item_count : usize,
+    pub current_room : u16,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ItemLocation
+{
+    Inventory,
+    Nowhere,
+    Room(u16),
+}
+
+#[derive(Debug, Clone)]
+pub struct Item
+{
+    pub address : u16,
+    pub name : String,
+    pub location : ItemLocation,
+}
+
+#[derive(Debug, Clone)]
+pub struct RoomInfo
+{
+    pub address : u16,
+    pub name : String,
+    pub description : String,
+    pub exits : Vec<String>,
+}
+
+impl GameLayout
+{
+    pub fn locate(memory : &Memory) -> Option<GameLayout>
+    {
+        let mut best : Option<(usize, usize)> = None;
+        let mut address = 0;
+        while address + ITEM_RECORD_SIZE <= memory.len()
+        {
+            let count = count_item_records(memory, address);
+            if count >= MIN_ITEM_COUNT && best.is_none_or(|(_, best_count)| count > best_count)
+            {
+                best = Some((address, count));
+            }
+            address += 1;
+        }
+
+        let (item_table, item_count) = best?;
+        let table_end = item_table + item_count * ITEM_RECORD_SIZE;
+        let current_room =
+            (table_end..memory.len())
+                .take(16)
+                .find(|&address| is_room_record(memory, memory[address]))?;
+
+        Some
+        (
+            GameLayout
+            {
+                item_table : item_table as u16,
+                item_count,
+                current_room : current_room as u16,
+            }
+        )
+    }
+
+    pub fn current_room(&self, memory : &Memory) -> Option<RoomInfo>
+    {
+        read_room(memory, memory[self.current_room as usize])
+    }
+
+    pub fn items(&self, memory : &Memory) -> Vec<Item>
+    {
+        (0..self.item_count)
+            .filter_map
+            (
+                |i|
+                {
+                    let address = self.item_table as usize + i * ITEM_RECORD_SIZE;
+                    let name = read_string(memory, memory[address])?;
+                    let location =
+                        match memory[address + 2]
+                        {
+                            IN_INVENTORY => ItemLocation::Inventory,
+                            NOWHERE => ItemLocation::Nowhere,
+                            room => ItemLocation::Room(room),
+                        };
+                    Some(Item { address : address as u16, name, location })
+                }
+            )
+            .collect()
+    }
+
+    pub fn inventory(&self, memory : &Memory) -> Vec<Item>
+    {
+        self.items(memory)
+            .into_iter()
+            .filter(|item| item.location == ItemLocation::Inventory)
+            .collect()
+    }
+
+    pub fn describe(&self, memory : &Memory) -> String
+    {
+        let mut text = String::new();
+        match self.current_room(memory)
+        {
+            Some(room) => text.push_str(&format!("room: {} ({})\n", room.name, room.address)),
+            None => text.push_str("room: unknown\n"),
+        }
+
+        let inventory : Vec<String> = self.inventory(memory).into_iter().map(|item| item.name).collect();
+        text.push_str(&format!("inventory: {}\n", inventory.join(", ")));
+
+        text.push_str("items:\n");
+        for item in self.items(memory)
+        {
+            let location =
+                match item.location
+                {
+                    ItemLocation::Inventory => "inventory".to_owned(),
+                    ItemLocation::Nowhere => "nowhere".to_owned(),
+                    ItemLocation::Room(address) =>
+                    {
+                        match read_room(memory, address)
+                        {
+                            Some(room) => format!("{} ({})", room.name, address),
+                            None => format!("{}", address),
+                        }
+                    },
+                };
+            text.push_str(&format!("  {}: {}\n", item.name, location));
+        }
+        text
+    }
+}
+
+fn count_item_records(memory : &Memory, table : usize) -> usize
+{
+    let mut count = 0;
+    while is_item_record(memory, table + count * ITEM_RECORD_SIZE)
+    {
+        count += 1;
+    }
+    count
+}
+
+fn is_item_record(memory : &Memory, address : usize) -> bool
+{
+    if address + ITEM_RECORD_SIZE > memory.len()
+    {
+        return false;
+    }
+    let location = memory[address + 2];
+    read_string(memory, memory[address]).is_some() &&
+        read_string(memory, memory[address + 1]).is_some() &&
+        (location == IN_INVENTORY || location == NOWHERE || is_room_record(memory, location))
+}
+
+fn is_room_record(memory : &Memory, address : u16) -> bool
+{
+    read_room(memory, address).is_some()
+}
+
+fn read_room(memory : &Memory, address : u16) -> Option<RoomInfo>
+{
+    let address = address as usize;
+    if address + 3 > memory.len()
+    {
+        return None;
+    }
+    let name = read_string(memory, memory[address])?;
+    let description = read_string(memory, memory[address + 1]).unwrap_or_default();
+    let exits = read_string_list(memory, memory[address + 2])?;
+    Some(RoomInfo { address : address as u16, name, description, exits })
+}
+
+fn read_string_list(memory : &Memory, address : u16) -> Option<Vec<String>>
+{
+    let length = memory.get(address as usize)?;
+    (0..length)
+        .map(|i| read_string(memory, memory.get(address as usize + 1 + i as usize)?))
+        .collect()
+}
+
+// Reads a length prefixed string, only accepting printable text.
+pub fn read_string(memory : &Memory, address : u16) -> Option<String>
+{
+    let length = memory.get(address as usize)?;
+    if length == 0 || length > MAX_STRING_LENGTH
+    {
+        return None;
+    }
+    let mut text = String::new();
+    for i in 1..=length as usize
+    {
+        let c = memory.get(address as usize + i)?;
+        if !(32..=126).contains(&c) && c != 10
+        {
+            return None;
+        }
+        text.push((c as u8) as char);
+    }
+    Some(text)
+}
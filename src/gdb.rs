@@ -0,0 +1,490 @@
+use std::collections::HashSet;
+use std::io;
+use std::io::{Read, Write, BufReader, BufWriter};
+use std::net::TcpListener;
+use std::sync::mpsc;
+use std::sync::mpsc::{Receiver, TryRecvError};
+use std::thread;
+use run_until::{StopConditions, StopReason};
+use vm::VM;
+
+const REGISTER_COUNT : usize = 8;
+const MEMORY_WORDS : usize = 32768;
+// Steps run between two checks for an interrupt from the debugger.
+const RUN_SLICE : u64 = 100_000;
+const INTERRUPT : u8 = 0x03;
+
+const TARGET_XML : &str = "<?xml version=\"1.0\"?>\
+<!DOCTYPE target SYSTEM \"gdb-target.dtd\">\
+<target version=\"1.0\">\
+<feature name=\"org.synacor.core\">\
+<reg name=\"r0\" bitsize=\"16\" type=\"uint16\"/>\
+<reg name=\"r1\" bitsize=\"16\" type=\"uint16\"/>\
+<reg name=\"r2\" bitsize=\"16\" type=\"uint16\"/>\
+<reg name=\"r3\" bitsize=\"16\" type=\"uint16\"/>\
+<reg name=\"r4\" bitsize=\"16\" type=\"uint16\"/>\
+<reg name=\"r5\" bitsize=\"16\" type=\"uint16\"/>\
+<reg name=\"r6\" bitsize=\"16\" type=\"uint16\"/>\
+<reg name=\"r7\" bitsize=\"16\" type=\"uint16\"/>\
+<reg name=\"pc\" bitsize=\"16\" type=\"code_ptr\"/>\
+</feature>\
+</target>";
+
+// GDB remote serial protocol server driving a VM.
+//
+// GDB addresses bytes, so memory is seen as it is laid out in challenge.bin:
+// word `w` is the little endian pair of bytes at `2 * w`, and the program
+// counter register holds the byte address of the current instruction.
+// Registers 0 to 7 are r0 to r7 and register 8 is the program counter.
+// Breakpoints are kept by the stub since there is no trap instruction.
+pub struct GdbStub
+{
+    vm : VM,
+    breakpoints : HashSet<u16>,
+}
+
+enum Reply
+{
+    Packet(String),
+    // Reply then close the connection.
+    Last(String),
+}
+
+impl GdbStub
+{
+    pub fn new(vm : VM) -> GdbStub
+    {
+        GdbStub { vm, breakpoints : HashSet::new() }
+    }
+
+    pub fn vm(&self) -> &VM
+    {
+        &self.vm
+    }
+
+    pub fn into_vm(self) -> VM
+    {
+        self.vm
+    }
+
+    // Serves a single debugger connection on `address`, e.g. "127.0.0.1:1234".
+    pub fn listen_tcp(&mut self, address : &str) -> io::Result<()>
+    {
+        let listener = TcpListener::bind(address)?;
+        println!("waiting for gdb on {}", listener.local_addr()?);
+        let (stream, _) = listener.accept()?;
+        stream.set_nodelay(true)?;
+        self.serve(stream.try_clone()?, stream)
+    }
+
+    #[cfg(unix)]
+    pub fn listen_unix(&mut self, path : &str) -> io::Result<()>
+    {
+        let listener = ::std::os::unix::net::UnixListener::bind(path)?;
+        println!("waiting for gdb on {}", path);
+        let (stream, _) = listener.accept()?;
+        self.serve(stream.try_clone()?, stream)
+    }
+
+    // Answers packets from `input` until the debugger detaches, kills the
+    // program or closes the connection. `input` is read on its own thread
+    // so that an interrupt can stop a `c` that does not return.
+    pub fn serve<R : Read + Send + 'static, W : Write>(&mut self, input : R, output : W) -> io::Result<()>
+    {
+        let input = read_in_background(input);
+        let mut output = BufWriter::new(output);
+        loop
+        {
+            let packet = match read_packet(&mut input.iter())?
+            {
+                None => return Ok(()),
+                Some(packet) => packet,
+            };
+            // Acknowledged right away, `c` may not reply for a long time.
+            output.write_all(b"+")?;
+            output.flush()?;
+            let reply = self.handle_packet(&packet, &input);
+            let (data, last) = match reply
+            {
+                Reply::Packet(data) => (data, false),
+                Reply::Last(data) => (data, true),
+            };
+            write!(output, "${}#{:02x}", data, checksum(data.as_bytes()))?;
+            output.flush()?;
+            if last
+            {
+                return Ok(());
+            }
+        }
+    }
+
+    fn handle_packet(&mut self, packet : &str, input : &Receiver<io::Result<u8>>) -> Reply
+    {
+        let reply = match packet.as_bytes().first()
+        {
+            Some(b'?') => "S05".to_owned(),
+            Some(b'g') => self.read_registers(),
+            Some(b'G') => self.write_registers(&packet[1..]),
+            Some(b'p') => self.read_register(&packet[1..]),
+            Some(b'P') => self.write_register(&packet[1..]),
+            Some(b'm') => self.read_memory(&packet[1..]),
+            Some(b'M') => self.write_memory(&packet[1..]),
+            Some(b'Z') => self.set_breakpoint(&packet[1..], true),
+            Some(b'z') => self.set_breakpoint(&packet[1..], false),
+            Some(b's') => self.resume(StopConditions::new().max_steps(1), input),
+            Some(b'c') => self.resume(StopConditions::new(), input),
+            Some(b'H') => "OK".to_owned(),
+            Some(b'D') => return Reply::Last("OK".to_owned()),
+            Some(b'k') => return Reply::Last(String::new()),
+            _ => self.query(packet),
+        };
+        Reply::Packet(reply)
+    }
+
+    fn query(&self, packet : &str) -> String
+    {
+        if packet.starts_with("qSupported")
+        {
+            "PacketSize=4000;qXfer:features:read+".to_owned()
+        }
+        else if let Some(range) = packet.strip_prefix("qXfer:features:read:target.xml:")
+        {
+            match parse_range(range)
+            {
+                Some((offset, length)) =>
+                {
+                    let start = offset.min(TARGET_XML.len());
+                    let end = (offset + length).min(TARGET_XML.len());
+                    let marker = if end == TARGET_XML.len() { 'l' } else { 'm' };
+                    format!("{}{}", marker, &TARGET_XML[start..end])
+                },
+                None => "E01".to_owned(),
+            }
+        }
+        else if packet == "qAttached"
+        {
+            "1".to_owned()
+        }
+        else if packet == "qfThreadInfo"
+        {
+            "m1".to_owned()
+        }
+        else if packet == "qsThreadInfo"
+        {
+            "l".to_owned()
+        }
+        else if packet == "qC"
+        {
+            "QC1".to_owned()
+        }
+        else
+        {
+            String::new()
+        }
+    }
+
+    fn register_value(&self, n : usize) -> Option<u16>
+    {
+        match n
+        {
+            0..=7 => Some(self.vm.registers()[n]),
+            8 => Some(self.vm.program_counter().wrapping_mul(2)),
+            _ => None,
+        }
+    }
+
+
+    fn read_registers(&self) -> String
+    {
+        (0..=REGISTER_COUNT).filter_map(|n| self.register_value(n)).map(hex_word).collect()
+    }
+
+    fn write_registers(&mut self, data : &str) -> String
+    {
+        match parse_hex_words(data)
+        {
+            // Written to a copy so that a rejected value changes nothing.
+            Some(ref words) if words.len() == REGISTER_COUNT + 1 =>
+            {
+                let mut vm = self.vm.clone();
+                if words.iter().enumerate().all(|(n, &value)| set_register_value(&mut vm, n, value))
+                {
+                    self.vm = vm;
+                    "OK".to_owned()
+                }
+                else
+                {
+                    "E01".to_owned()
+                }
+            },
+            _ => "E01".to_owned(),
+        }
+    }
+
+    fn read_register(&self, data : &str) -> String
+    {
+        match usize::from_str_radix(data, 16).ok().and_then(|n| self.register_value(n))
+        {
+            Some(value) => hex_word(value),
+            None => "E01".to_owned(),
+        }
+    }
+
+    fn write_register(&mut self, data : &str) -> String
+    {
+        let parsed =
+            data.split_once('=')
+                .and_then(|(n, value)| Some((usize::from_str_radix(n, 16).ok()?, parse_hex_words(value)?)));
+        match parsed
+        {
+            Some((n, ref words)) if words.len() == 1 && set_register_value(&mut self.vm, n, words[0]) => "OK".to_owned(),
+            _ => "E01".to_owned(),
+        }
+    }
+
+    fn read_memory(&self, data : &str) -> String
+    {
+        let (address, length) = match parse_range(data)
+        {
+            Some(range) => range,
+            None => return "E01".to_owned(),
+        };
+        let mut reply = String::new();
+        for byte_address in address..address + length
+        {
+            match self.vm.memory().get(byte_address / 2)
+            {
+                Some(word) if byte_address / 2 < MEMORY_WORDS =>
+                    reply.push_str(&format!("{:02x}", word.to_le_bytes()[byte_address % 2])),
+                _ => break,
+            }
+        }
+        if reply.is_empty() && length > 0
+        {
+            "E01".to_owned()
+        }
+        else
+        {
+            reply
+        }
+    }
+
+    // Writes go through `VM::write_memory` so caches are invalidated like
+    // for a `wmem`. Odd byte counts update one half of the last word.
+    fn write_memory(&mut self, data : &str) -> String
+    {
+        let parsed =
+            data.split_once(':')
+                .and_then(|(range, bytes)| Some((parse_range(range)?, parse_hex_bytes(bytes)?)));
+        let ((address, length), bytes) = match parsed
+        {
+            Some(parsed) => parsed,
+            None => return "E01".to_owned(),
+        };
+        if bytes.len() != length
+        {
+            return "E01".to_owned();
+        }
+        for (i, &byte) in bytes.iter().enumerate()
+        {
+            let byte_address = address + i;
+            let word_address = byte_address / 2;
+            let mut word = match self.vm.memory().get(word_address)
+            {
+                Some(word) if word_address < MEMORY_WORDS => word.to_le_bytes(),
+                _ => return "E01".to_owned(),
+            };
+            word[byte_address % 2] = byte;
+            if self.vm.write_memory(word_address as u16, u16::from_le_bytes(word)).is_err()
+            {
+                return "E01".to_owned();
+            }
+        }
+        "OK".to_owned()
+    }
+
+    fn set_breakpoint(&mut self, data : &str, insert : bool) -> String
+    {
+        let mut fields = data.split(',');
+        let kind = fields.next();
+        let address = fields.next().and_then(|address| usize::from_str_radix(address, 16).ok());
+        match (kind, address)
+        {
+            (Some("0"), Some(address)) | (Some("1"), Some(address)) =>
+            {
+                let word_address = (address / 2) as u16;
+                if insert
+                {
+                    self.breakpoints.insert(word_address);
+                }
+                else
+                {
+                    self.breakpoints.remove(&word_address);
+                }
+                "OK".to_owned()
+            },
+            // Watchpoints are not supported.
+            _ => String::new(),
+        }
+    }
+
+    // Runs in slices of `RUN_SLICE` steps, looking for an interrupt from
+    // the debugger in between.
+    fn resume(&mut self, conditions : StopConditions, input : &Receiver<io::Result<u8>>) -> String
+    {
+        let mut conditions = self.breakpoints.iter().fold(conditions, |conditions, &address| conditions.stop_at(address));
+        let last_step = conditions.max_steps.map(|max_steps| self.vm.step_nb().saturating_add(max_steps));
+        loop
+        {
+            let remaining = last_step.map_or(RUN_SLICE, |last_step| last_step - self.vm.step_nb());
+            conditions.max_steps = Some(remaining.min(RUN_SLICE));
+            match self.vm.run(&conditions)
+            {
+                StopReason::StepBudgetExhausted if last_step != Some(self.vm.step_nb()) =>
+                {
+                    if is_interrupted(input)
+                    {
+                        // SIGINT
+                        return "S02".to_owned();
+                    }
+                },
+                StopReason::Halted => return "W00".to_owned(),
+                // SIGILL
+                StopReason::Fault(_) => return "S04".to_owned(),
+                // SIGTRAP
+                _ => return "S05".to_owned(),
+            }
+        }
+    }
+}
+
+// False when there is no register `n` or the VM rejects the value.
+fn set_register_value(vm : &mut VM, n : usize, value : u16) -> bool
+{
+    match n
+    {
+        0..=7 => vm.set_register(n, value).is_ok(),
+        8 =>
+        {
+            vm.set_program_counter(value / 2);
+            true
+        },
+        _ => false,
+    }
+}
+
+// Sends the bytes of `input` over a channel, which is closed at the end
+// of the stream or after the first error.
+fn read_in_background<R : Read + Send + 'static>(input : R) -> Receiver<io::Result<u8>>
+{
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn
+    (
+        move ||
+        {
+            for byte in BufReader::new(input).bytes()
+            {
+                let is_error = byte.is_err();
+                if sender.send(byte).is_err() || is_error
+                {
+                    break;
+                }
+            }
+        }
+    );
+    receiver
+}
+
+// True once the debugger sent an interrupt or went away. gdb sends nothing
+// else while the program runs, other bytes are dropped.
+fn is_interrupted(input : &Receiver<io::Result<u8>>) -> bool
+{
+    loop
+    {
+        match input.try_recv()
+        {
+            Ok(Ok(INTERRUPT)) | Ok(Err(_)) | Err(TryRecvError::Disconnected) => return true,
+            Ok(Ok(_)) => (),
+            Err(TryRecvError::Empty) => return false,
+        }
+    }
+}
+
+// Reads the next `$...#xx` packet, skipping acknowledgments and interrupts
+// sent while the program is stopped. Retransmission requests are ignored
+// too, local connections do not lose data. Returns None once the
+// connection is closed.
+fn read_packet<I>(input : &mut I) -> io::Result<Option<String>>
+    where I : Iterator<Item = io::Result<u8>>
+{
+    loop
+    {
+        match input.next().transpose()?
+        {
+            None => return Ok(None),
+            Some(b'$') => break,
+            Some(_) => (),
+        }
+    }
+
+    let mut data = vec!();
+    loop
+    {
+        match input.next().transpose()?
+        {
+            None => return Ok(None),
+            Some(b'#') => break,
+            Some(byte) => data.push(byte),
+        }
+    }
+    // The checksum is not verified either.
+    for _ in 0..2
+    {
+        if input.next().transpose()?.is_none()
+        {
+            return Ok(None);
+        }
+    }
+    Ok(Some(String::from_utf8_lossy(&data).into_owned()))
+}
+
+pub fn checksum(data : &[u8]) -> u8
+{
+    data.iter().fold(0u8, |sum, &byte| sum.wrapping_add(byte))
+}
+
+// A word in target byte order.
+fn hex_word(value : u16) -> String
+{
+    let bytes = value.to_le_bytes();
+    format!("{:02x}{:02x}", bytes[0], bytes[1])
+}
+
+fn parse_hex_bytes(data : &str) -> Option<Vec<u8>>
+{
+    if !data.len().is_multiple_of(2)
+    {
+        return None;
+    }
+    (0..data.len()).step_by(2).map(|i| u8::from_str_radix(data.get(i..i + 2)?, 16).ok()).collect()
+}
+
+fn parse_hex_words(data : &str) -> Option<Vec<u16>>
+{
+    let bytes = parse_hex_bytes(data)?;
+    if !bytes.len().is_multiple_of(2)
+    {
+        return None;
+    }
+    Some(bytes.chunks(2).map(|pair| u16::from_le_bytes([pair[0], pair[1]])).collect())
+}
+
+// "addr,length" in hex. None when the range does not fit in a usize.
+fn parse_range(data : &str) -> Option<(usize, usize)>
+{
+    let (address, length) = data.split_once(',')?;
+    let address = usize::from_str_radix(address, 16).ok()?;
+    let length = usize::from_str_radix(length, 16).ok()?;
+    address.checked_add(length)?;
+    Some((address, length))
+}
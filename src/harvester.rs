@@ -0,0 +1,133 @@
+use std::collections::HashSet;
+use std::fs::File;
+use std::fs::OpenOptions;
+use std::io::BufRead;
+use std::io::BufReader;
+use std::io::Write;
+
+const CODE_LENGTH : usize = 12;
+
+#[derive(Debug, Clone)]
+pub struct HarvestedCode
+{
+    pub code : String,
+    pub step : u64,
+    pub last_input : String,
+}
+
+// Watches the characters printed by the VM and records every string that
+// looks like a challenge code. Codes are appended to `file_name` as
+// tab separated `code step last_input` lines, each code only once.
+#[derive(Clone)]
+pub struct CodeHarvester
+{
+    file_name : String,
+    known_codes : HashSet<String>,
+    current_word : String,
+    last_input : String,
+    harvested : Vec<HarvestedCode>,
+}
+
+impl CodeHarvester
+{
+    pub fn new(file_name : &str) -> CodeHarvester
+    {
+        let mut known_codes = HashSet::new();
+        if let Ok(file) = File::open(file_name)
+        {
+            for line in BufReader::new(file).lines().map_while(Result::ok)
+            {
+                if let Some(code) = line.split('\t').next()
+                {
+                    if !code.is_empty()
+                    {
+                        known_codes.insert(code.to_owned());
+                    }
+                }
+            }
+        }
+
+        CodeHarvester
+        {
+            file_name : file_name.to_owned(),
+            known_codes,
+            current_word : String::new(),
+            last_input : String::new(),
+            harvested : vec!(),
+        }
+    }
+
+    pub fn harvested(&self) -> &[HarvestedCode]
+    {
+        &self.harvested
+    }
+
+    pub fn on_input_line(&mut self, line : &str)
+    {
+        self.last_input = line.trim().to_owned();
+    }
+
+    pub fn on_output_char(&mut self, c : char, step : u64)
+    {
+        if c.is_ascii_alphanumeric()
+        {
+            self.current_word.push(c);
+        }
+        else
+        {
+            self.flush_word(step);
+        }
+    }
+
+    fn flush_word(&mut self, step : u64)
+    {
+        if is_code(&self.current_word) && !self.known_codes.contains(&self.current_word)
+        {
+            let harvested_code =
+                HarvestedCode
+                {
+                    code : self.current_word.clone(),
+                    step,
+                    last_input : self.last_input.clone(),
+                };
+            self.append_to_file(&harvested_code);
+            self.known_codes.insert(harvested_code.code.clone());
+            self.harvested.push(harvested_code);
+        }
+        self.current_word.clear();
+    }
+
+    fn append_to_file(&self, harvested_code : &HarvestedCode)
+    {
+        let f = OpenOptions::new().create(true).append(true).open(&self.file_name);
+        match f
+        {
+            Err(e) => eprintln!("cannot save code {}: {}", harvested_code.code, e),
+            Ok(mut file) =>
+            {
+                let line =
+                    format!
+                    (
+                        "{}\t{}\t{}\n",
+                        harvested_code.code,
+                        harvested_code.step,
+                        harvested_code.last_input
+                    );
+                if let Err(e) = file.write_all(line.as_bytes())
+                {
+                    eprintln!("cannot save code {}: {}", harvested_code.code, e);
+                }
+            },
+        }
+    }
+}
+
+// Codes are 12 letters or digits mixing upper and lower case past the first
+// character, which rules out ordinary capitalized words.
+pub fn is_code(word : &str) -> bool
+{
+    word.len() == CODE_LENGTH &&
+        word.chars().all(|c| c.is_ascii_alphanumeric()) &&
+        word.chars().skip(1).any(|c| c.is_ascii_uppercase()) &&
+        word.chars().any(|c| c.is_ascii_lowercase())
+}
@@ -0,0 +1,91 @@
+use std::fmt::Write;
+
+// Words shown per side of a range before the rest is elided.
+const MAX_WORDS_SHOWN : usize = 16;
+
+// Consecutive words that differ between two images. A side is None past
+// the end of its image.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChangedRange
+{
+    pub start : usize,
+    pub before : Vec<Option<u16>>,
+    pub after : Vec<Option<u16>>,
+}
+
+impl ChangedRange
+{
+    pub fn end(&self) -> usize
+    {
+        self.start + self.before.len()
+    }
+}
+
+pub fn diff(before : &[u16], after : &[u16]) -> Vec<ChangedRange>
+{
+    let mut ranges : Vec<ChangedRange> = vec!();
+    for address in 0..before.len().max(after.len())
+    {
+        let old = before.get(address).cloned();
+        let new = after.get(address).cloned();
+        if old == new
+        {
+            continue;
+        }
+        match ranges.last_mut()
+        {
+            Some(ref mut range) if range.end() == address =>
+            {
+                range.before.push(old);
+                range.after.push(new);
+            },
+            _ => ranges.push(ChangedRange { start : address, before : vec!(old), after : vec!(new) }),
+        }
+    }
+    ranges
+}
+
+// One paragraph per range: its bounds, the old and new words and the new
+// words read as characters, which shows decrypted text.
+pub fn describe(ranges : &[ChangedRange]) -> String
+{
+    let changed : usize = ranges.iter().map(|range| range.before.len()).sum();
+    let mut text = format!("{} words changed in {} ranges\n", changed, ranges.len());
+    for range in ranges
+    {
+        let _ = writeln!(text, "{}..{}: {} words", range.start, range.end(), range.before.len());
+        let _ = writeln!(text, "  - {}", words(&range.before));
+        let _ = writeln!(text, "  + {}", words(&range.after));
+        let ascii : String =
+            range.after
+                .iter()
+                .take(MAX_WORDS_SHOWN * 4)
+                .map
+                (
+                    |word|
+                        match *word
+                        {
+                            Some(word) if (0x20..0x7f).contains(&word) => word as u8 as char,
+                            _ => '.',
+                        }
+                )
+                .collect();
+        let _ = writeln!(text, "    {}", ascii);
+    }
+    text
+}
+
+fn words(words : &[Option<u16>]) -> String
+{
+    let mut shown : Vec<String> =
+        words
+            .iter()
+            .take(MAX_WORDS_SHOWN)
+            .map(|word| word.map_or("----".to_owned(), |word| format!("{:04x}", word)))
+            .collect();
+    if words.len() > MAX_WORDS_SHOWN
+    {
+        shown.push("...".to_owned());
+    }
+    shown.join(" ")
+}
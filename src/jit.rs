@@ -0,0 +1,364 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use memory::Memory;
+use opcode;
+use opcode::{OpCode, ParsedNumber, check_number};
+use vm::{VM, RunFailure};
+
+// Blocks stop at the first instruction that may leave the straight line
+// (jumps, calls, returns, halt, input) or that may rewrite code (wmem), or
+// once they reach this many instructions.
+const MAX_BLOCK_SIZE : usize = 64;
+
+const MAX_OP_CODE : u16 = 21;
+
+pub type Instruction = Box<dyn Fn(&mut VM) -> Result<(), RunFailure> + Send + Sync>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend
+{
+    Interpreter,
+    BlockJit,
+}
+
+// A straight run of instructions translated to closures with their
+// operands already resolved. Each closure runs one instruction, including
+// the program counter update.
+pub struct Block
+{
+    pub start : u16,
+    pub end : u16,
+    pub instructions : Vec<Instruction>,
+}
+
+#[derive(Clone, Copy)]
+enum Operand
+{
+    Literal(u16),
+    Register(usize),
+}
+
+impl Operand
+{
+    fn new(number : u16) -> Option<Operand>
+    {
+        match check_number(number)
+        {
+            ParsedNumber::LiteralValue(value) => Some(Operand::Literal(value)),
+            ParsedNumber::Register(r) => Some(Operand::Register(r as usize)),
+            ParsedNumber::InvalidNumber => None,
+        }
+    }
+
+    fn read(&self, vm : &VM, op_code : OpCode) -> Result<u16, RunFailure>
+    {
+        match *self
+        {
+            Operand::Literal(value) => Ok(value),
+            Operand::Register(r) => vm.read_register(op_code, r),
+        }
+    }
+}
+
+fn register(number : u16) -> Option<usize>
+{
+    match check_number(number)
+    {
+        ParsedNumber::Register(r) => Some(r as usize),
+        _ => None,
+    }
+}
+
+fn is_block_end(op_code : &OpCode) -> bool
+{
+    matches!
+    (
+        *op_code,
+        OpCode::Halt |
+        OpCode::Jump(_) |
+        OpCode::JumpNotZero(_) |
+        OpCode::JumpZero(_) |
+        OpCode::Call(_) |
+        OpCode::Return |
+        OpCode::In(_) |
+        OpCode::WriteMemory(_)
+    )
+}
+
+// Translates the block starting at `start`. Returns None when the first
+// instruction cannot be decoded, the interpreter then reports the failure.
+pub fn compile_block(memory : &Memory, start : u16) -> Option<Block>
+{
+    let mut instructions : Vec<Instruction> = vec!();
+    let mut address = start;
+    // The decoder reports unknown op codes on stderr, only hand it words
+    // that are op codes so translating ahead stays silent.
+    while memory.get(address as usize).is_some_and(|word| word <= MAX_OP_CODE)
+    {
+        let op_code = match opcode::read_memory_to_op_code(memory, address)
+        {
+            Ok(op_code) => op_code,
+            Err(_) => break,
+        };
+        // An instruction that runs past the end of the address space is
+        // left to the interpreter, a block never wraps around.
+        let next = match address.checked_add(op_code.size())
+        {
+            Some(next) => next,
+            None => break,
+        };
+        let is_end = is_block_end(&op_code);
+        instructions.push(translate(op_code, next));
+        address = next;
+        if is_end || instructions.len() >= MAX_BLOCK_SIZE
+        {
+            break;
+        }
+    }
+
+    if instructions.is_empty()
+    {
+        None
+    }
+    else
+    {
+        Some(Block { start, end : address, instructions })
+    }
+}
+
+// Instructions that only touch registers, the stack and the program counter
+// get a dedicated closure; the others call back into the interpreter with
+// the already decoded op code so both backends share one implementation.
+fn translate(op_code : OpCode, next : u16) -> Instruction
+{
+    match op_code
+    {
+        OpCode::Noop =>
+            Box::new(move |vm : &mut VM| { vm.program_counter = next; Ok(()) }),
+        OpCode::Jump(jump) if check_number(jump.value).is_literal_value() =>
+        {
+            let target = jump.value;
+            Box::new(move |vm : &mut VM| { vm.program_counter = target; Ok(()) })
+        },
+        OpCode::JumpNotZero(jump) if check_number(jump.jump_location).is_literal_value() =>
+        {
+            match Operand::new(jump.value)
+            {
+                Some(value) =>
+                {
+                    let target = jump.jump_location;
+                    Box::new
+                    (
+                        move |vm : &mut VM|
+                        {
+                            vm.program_counter = if value.read(vm, op_code)? != 0 { target } else { next };
+                            Ok(())
+                        }
+                    )
+                },
+                None => interpret(op_code),
+            }
+        },
+        OpCode::JumpZero(jump) if check_number(jump.jump_location).is_literal_value() =>
+        {
+            match Operand::new(jump.value)
+            {
+                Some(value) =>
+                {
+                    let target = jump.jump_location;
+                    Box::new
+                    (
+                        move |vm : &mut VM|
+                        {
+                            vm.program_counter = if value.read(vm, op_code)? == 0 { target } else { next };
+                            Ok(())
+                        }
+                    )
+                },
+                None => interpret(op_code),
+            }
+        },
+        OpCode::SetRegister(set) =>
+        {
+            match (register(set.register), Operand::new(set.value))
+            {
+                (Some(r), Some(value)) =>
+                    Box::new
+                    (
+                        move |vm : &mut VM|
+                        {
+                            vm.register[r] = value.read(vm, op_code)?;
+                            vm.program_counter = next;
+                            Ok(())
+                        }
+                    ),
+                _ => interpret(op_code),
+            }
+        },
+        OpCode::Push(push) =>
+        {
+            match Operand::new(push.value)
+            {
+                Some(value) =>
+                    Box::new
+                    (
+                        move |vm : &mut VM|
+                        {
+                            let value = value.read(vm, op_code)?;
+                            vm.stack.push(value);
+                            vm.program_counter = next;
+                            Ok(())
+                        }
+                    ),
+                None => interpret(op_code),
+            }
+        },
+        OpCode::IsEqual(op) =>
+            binary(op_code, op.cell_result, op.first_operand, op.second_operand, next, |b, c| (b == c) as u16),
+        OpCode::IsGreaterThan(op) =>
+            binary(op_code, op.cell_result, op.first_operand, op.second_operand, next, |b, c| (b > c) as u16),
+        OpCode::Add(op) =>
+            binary(op_code, op.cell_result, op.first_operand, op.second_operand, next, |b, c| ((b as u32 + c as u32) % 32768) as u16),
+        OpCode::Multiply(op) =>
+            binary(op_code, op.cell_result, op.first_operand, op.second_operand, next, |b, c| ((b as u32 * c as u32) % 32768) as u16),
+        OpCode::And(op) =>
+            binary(op_code, op.cell_result, op.first_operand, op.second_operand, next, |b, c| b & c),
+        OpCode::Or(op) =>
+            binary(op_code, op.cell_result, op.first_operand, op.second_operand, next, |b, c| b | c),
+        OpCode::Not(not) =>
+        {
+            match (register(not.cell_result), Operand::new(not.operand))
+            {
+                (Some(r), Some(value)) =>
+                    Box::new
+                    (
+                        move |vm : &mut VM|
+                        {
+                            vm.register[r] = !value.read(vm, op_code)? & 0b0111_1111_1111_1111;
+                            vm.program_counter = next;
+                            Ok(())
+                        }
+                    ),
+                _ => interpret(op_code),
+            }
+        },
+        _ => interpret(op_code),
+    }
+}
+
+fn binary<F>
+(
+    op_code : OpCode,
+    cell_result : u16,
+    first_operand : u16,
+    second_operand : u16,
+    next : u16,
+    operation : F
+) -> Instruction
+    where F : Fn(u16, u16) -> u16 + Send + Sync + 'static
+{
+    match (register(cell_result), Operand::new(first_operand), Operand::new(second_operand))
+    {
+        (Some(r), Some(b), Some(c)) =>
+            Box::new
+            (
+                move |vm : &mut VM|
+                {
+                    let b = b.read(vm, op_code)?;
+                    let c = c.read(vm, op_code)?;
+                    vm.register[r] = operation(b, c);
+                    vm.program_counter = next;
+                    Ok(())
+                }
+            ),
+        _ => interpret(op_code),
+    }
+}
+
+fn interpret(op_code : OpCode) -> Instruction
+{
+    Box::new(move |vm : &mut VM| vm.handle_op_code(op_code))
+}
+
+// Translated blocks keyed by their start address. `covered` marks every
+// word that belongs to a block so that data writes skip the block search.
+pub struct BlockCache
+{
+    blocks : HashMap<u16, Arc<Block>>,
+    covered : Vec<bool>,
+}
+
+impl BlockCache
+{
+    pub fn new() -> BlockCache
+    {
+        BlockCache
+        {
+            blocks : HashMap::new(),
+            covered : vec!(),
+        }
+    }
+
+    pub fn get(&self, address : u16) -> Option<Arc<Block>>
+    {
+        self.blocks.get(&address).cloned()
+    }
+
+    pub fn insert(&mut self, block : Arc<Block>)
+    {
+        self.mark(&block);
+        self.blocks.insert(block.start, block);
+    }
+
+    pub fn invalidate(&mut self, address : u16)
+    {
+        if !self.covered.get(address as usize).cloned().unwrap_or(false)
+        {
+            return;
+        }
+
+        self.blocks.retain(|_, block| address < block.start || address >= block.end);
+        self.covered.clear();
+        let blocks : Vec<Arc<Block>> = self.blocks.values().cloned().collect();
+        for block in blocks
+        {
+            self.mark(&block);
+        }
+    }
+
+    pub fn clear(&mut self)
+    {
+        self.blocks.clear();
+        self.covered.clear();
+    }
+
+    fn mark(&mut self, block : &Block)
+    {
+        let end = block.end as usize;
+        if end > self.covered.len()
+        {
+            self.covered.resize(end, false);
+        }
+        for covered in &mut self.covered[block.start as usize..end]
+        {
+            *covered = true;
+        }
+    }
+}
+
+impl Default for BlockCache
+{
+    fn default() -> BlockCache
+    {
+        BlockCache::new()
+    }
+}
+
+// Like the decode cache, a clone translates its blocks again as it runs.
+impl Clone for BlockCache
+{
+    fn clone(&self) -> BlockCache
+    {
+        BlockCache::new()
+    }
+}
@@ -0,0 +1,27 @@
+pub mod opcode;
+pub mod loader;
+pub mod vm;
+pub mod memory;
+pub mod memory_view;
+pub mod patch;
+pub mod image_diff;
+pub mod state_diff;
+pub mod stack;
+pub mod state_hash;
+pub mod conformance;
+pub mod decode_cache;
+pub mod jit;
+pub mod run_until;
+pub mod reference;
+pub mod gdb;
+pub mod symbols;
+pub mod dap;
+pub mod disassembler;
+pub mod tui;
+pub mod harvester;
+pub mod code_watch;
+pub mod transcript;
+pub mod console;
+pub mod explorer;
+pub mod planner;
+pub mod game_state;
@@ -0,0 +1,75 @@
+extern crate byteorder;
+
+use std::fs::File;
+use std::io::Read;
+use std::io::Write;
+use std::io::Cursor;
+use self::byteorder::{LittleEndian, ReadBytesExt};
+
+#[derive(Debug)]
+pub enum ConvertToU16Error
+{
+    NotEvenNumberOfBytes
+}
+
+pub fn convert_to_u16_le(mem : &Vec<u8>) -> 
+    Result<Vec<u16>, ConvertToU16Error>
+{
+    let size = mem.len();
+    if !size.is_multiple_of(2) 
+    {
+        Err(ConvertToU16Error::NotEvenNumberOfBytes)
+    } 
+    else
+    {
+        let mut mem_u16 : Vec<u16> = vec!();
+        let mut rdr = Cursor::new(mem);
+        let mut keep_going = true;
+        while keep_going
+        {
+            let u16_result = rdr.read_u16::<LittleEndian>();
+            match u16_result
+            {
+                Err(_) => keep_going = false,
+                Ok(value) => mem_u16.push(value),
+            }
+        }
+        Ok(mem_u16)
+    }
+}
+
+pub fn read_challenge_file(file_name: &str) -> 
+    Result<Vec<u8>, std::io::Error>
+{
+    let mut file = File::open(file_name)?;
+    let mut content : Vec<u8> = vec!();
+    let result = file.read_to_end(&mut content);
+    result.map(|_| content)
+}
+
+#[derive(Debug)]
+pub enum ReadImageError
+{
+    CannotRead(std::io::Error),
+    Invalid(ConvertToU16Error),
+}
+
+pub fn read_image_file(file_name : &str) -> 
+    Result<Vec<u16>, ReadImageError>
+{
+    let content = read_challenge_file(file_name).map_err(ReadImageError::CannotRead)?;
+    convert_to_u16_le(&content).map_err(ReadImageError::Invalid)
+}
+
+// The inverse of `convert_to_u16_le`.
+pub fn convert_to_bytes_le(mem : &[u16]) -> Vec<u8>
+{
+    mem.iter().flat_map(|word| word.to_le_bytes().to_vec()).collect()
+}
+
+pub fn write_image_file(file_name : &str, mem : &[u16]) -> 
+    Result<(), std::io::Error>
+{
+    let mut file = File::create(file_name)?;
+    file.write_all(&convert_to_bytes_le(mem))
+}
@@ -1,14 +1,56 @@
-mod opcode;
-mod vm;
+extern crate synacor_challenge;
 
-use std::fs::File;
-use std::io::Read;
-use std::io::Cursor;
-extern crate byteorder;
-use byteorder::{LittleEndian, ReadBytesExt};
+use synacor_challenge::vm;
+use synacor_challenge::jit::Backend;
+use synacor_challenge::loader::{read_challenge_file, read_image_file, convert_to_u16_le};
+use synacor_challenge::image_diff;
+use synacor_challenge::state_diff;
+use synacor_challenge::state_diff::SavedState;
+use synacor_challenge::harvester::CodeHarvester;
+use synacor_challenge::code_watch::CodeWatch;
+use synacor_challenge::console::Input;
+use synacor_challenge::transcript;
+use synacor_challenge::transcript::Transcript;
+use synacor_challenge::gdb::GdbStub;
+use synacor_challenge::dap::DapServer;
+use synacor_challenge::tui::Tui;
+use synacor_challenge::patch::{Patch, PatchFailure, image_checksum, apply_at_load, run_patched};
+use synacor_challenge::run_until::StopReason;
 
 fn main() 
 {
+    // The editor names the program in its launch request.
+    if std::env::args().any(|arg| arg == "--dap")
+    {
+        let stdin = std::io::BufReader::new(std::io::stdin());
+        if let Err(e) = DapServer::new().serve(stdin, std::io::stdout())
+        {
+            eprintln!("dap connection failed: {}", e);
+        }
+        return;
+    }
+
+    let args : Vec<String> = std::env::args().collect();
+    if let Some(i) = args.iter().position(|arg| arg == "--diff-states")
+    {
+        match (args.get(i + 1), args.get(i + 2))
+        {
+            (Some(before), Some(after)) => diff_states(before, after, args.iter().any(|arg| arg == "--json")),
+            _ => println!("usage: --diff-states <dump dir> <dump dir> [--json]"),
+        }
+        return;
+    }
+    if let Some(i) = args.iter().position(|arg| arg == "--diff-transcripts")
+    {
+        match (args.get(i + 1).map(|a| transcript::load(a)), args.get(i + 2).map(|b| transcript::load(b)))
+        {
+            (Some(Ok(a)), Some(Ok(b))) => print!("{}", transcript::describe_difference(&a, &b)),
+            (Some(Err(e)), _) | (_, Some(Err(e))) => println!("cannot load transcript: {:?}", e),
+            _ => println!("usage: --diff-transcripts <transcript> <transcript>"),
+        }
+        return;
+    }
+
     let file_name = "challenge.bin";
     let result = read_challenge_file(file_name);
     match result 
@@ -22,23 +64,87 @@ fn main()
                 Err(e) => println!("File appear to be invalid: {:?}", e),
                 Ok(mem) =>
                 {
-                    let mut vm = vm::VM::new(mem);
-                    let mut result = vm.step();
-                    let mut should_continue = true;
-
-                    while should_continue && !result.is_err()
+                    if args.iter().any(|arg| arg == "--image-checksum")
                     {
-                        if !should_continue
+                        println!("{:016x}", image_checksum(&mem));
+                        return;
+                    }
+                    if let Some(other) = args.iter().position(|arg| arg == "--diff-image").and_then(|i| args.get(i + 1))
+                    {
+                        match read_image_file(other)
+                        {
+                            Ok(image) => print!("{}", image_diff::describe(&image_diff::diff(&mem, &image))),
+                            Err(e) => println!("cannot read {}: {:?}", other, e),
+                        }
+                        return;
+                    }
+                    let mut vm = vm::VM::new(mem.clone());
+                    vm.set_code_harvester(CodeHarvester::new("data/codes.tsv"));
+                    if args.iter().any(|arg| arg == "--jit")
+                    {
+                        vm.set_backend(Backend::BlockJit);
+                    }
+                    if args.iter().any(|arg| arg == "--watch-code")
+                    {
+                        vm.set_code_watch(CodeWatch::new());
+                    }
+                    if let Some(file_name) = args.iter().position(|arg| arg == "--transcript").and_then(|i| args.get(i + 1))
+                    {
+                        vm.set_transcript(Transcript::new(file_name));
+                    }
+                    // The input lines of a transcript instead of the terminal.
+                    if let Some(file_name) = args.iter().position(|arg| arg == "--replay").and_then(|i| args.get(i + 1))
+                    {
+                        match transcript::load(file_name)
                         {
-                            continue;
+                            Ok(records) =>
+                            {
+                                let lines = transcript::input_script(&records).into_iter().map(|line| line + "\n").collect();
+                                vm.set_input(Input::Queue(lines));
+                            },
+                            Err(e) =>
+                            {
+                                println!("cannot load transcript: {:?}", e);
+                                return;
+                            },
                         }
-                        
-                        result = vm.step();
                     }
-                    if result.is_err()
+                    // Patches with a step are only applied by a plain run.
+                    let scheduled = match load_patches(&args, &mut vm, &mem)
+                    {
+                        Ok(scheduled) => scheduled,
+                        Err(e) =>
+                        {
+                            println!("cannot apply patches: {:?}", e);
+                            return;
+                        },
+                    };
+                    if let Some(address) = args.iter().position(|arg| arg == "--gdb").and_then(|i| args.get(i + 1))
                     {
-                        let err = result.unwrap_err();
-                        println!("{:?}", err);
+                        serve_gdb(vm, address);
+                        return;
+                    }
+                    if args.iter().any(|arg| arg == "--tui")
+                    {
+                        if let Err(e) = Tui::new(vm).run()
+                        {
+                            println!("terminal failure: {}", e);
+                        }
+                        return;
+                    }
+                    match run_patched(&mut vm, &scheduled)
+                    {
+                        Ok(StopReason::Halted) => (),
+                        Ok(reason) => println!("{:?}", reason),
+                        Err(e) => println!("cannot apply patch: {:?}", e),
+                    }
+                    if let Some(code_watch) = vm.code_watch()
+                    {
+                        print!("{}", code_watch.report());
+                    }
+                    if let Some(mut transcript) = vm.take_transcript()
+                    {
+                        transcript.finish();
                     }
                 }
             }
@@ -46,46 +152,62 @@ fn main()
     }
 }
 
-#[derive(Debug)]
-enum ConvertToU16Error
+fn diff_states(before : &str, after : &str, as_json : bool)
 {
-    NotEvenNumberOfBytes
-}
-
-fn convert_to_u16_le(mem : &Vec<u8>) -> 
-    Result<Vec<u16>, ConvertToU16Error>
-{
-    let size = mem.len();
-    if size % 2 != 0 
-    {
-        Err(ConvertToU16Error::NotEvenNumberOfBytes)
-    } 
-    else
+    match (SavedState::load(before), SavedState::load(after))
     {
-        let mut mem_u16 : Vec<u16> = vec!();
-        let mut rdr = Cursor::new(mem);
-        let mut keep_going = true;
-        while keep_going
+        (Ok(before), Ok(after)) =>
         {
-            let u16_result = rdr.read_u16::<LittleEndian>();
-            if u16_result.is_err() 
+            let diff = state_diff::diff(&before, &after);
+            if as_json
             {
-                keep_going = false;
+                println!("{}", diff.to_json());
             }
             else
             {
-                mem_u16.push(u16_result.unwrap());
+                print!("{}", diff.to_text());
             }
-        }
-        Ok(mem_u16)
+        },
+        (Err(e), _) | (_, Err(e)) => println!("cannot load state: {:?}", e),
+    }
+}
+
+// Every `--patch <file>`, those without a step are applied now and the
+// others returned.
+fn load_patches(args : &[String], vm : &mut vm::VM, image : &[u16]) -> Result<Vec<Patch>, PatchFailure>
+{
+    let patches =
+        args.windows(2)
+            .filter(|pair| pair[0] == "--patch")
+            .map(|pair| Patch::load(&pair[1]))
+            .collect::<Result<Vec<Patch>, PatchFailure>>()?;
+    apply_at_load(vm, image, patches)
+}
+
+// `address` is "host:port" or "unix:<path>".
+fn serve_gdb(vm : vm::VM, address : &str)
+{
+    let mut stub = GdbStub::new(vm);
+    let result =
+        match address.strip_prefix("unix:")
+        {
+            Some(path) => listen_unix(&mut stub, path),
+            None => stub.listen_tcp(address),
+        };
+    if let Err(e) = result
+    {
+        println!("gdb connection failed: {}", e);
     }
 }
 
-fn read_challenge_file(file_name: &str) -> 
-    Result<Vec<u8>, std::io::Error>
+#[cfg(unix)]
+fn listen_unix(stub : &mut GdbStub, path : &str) -> std::io::Result<()>
+{
+    stub.listen_unix(path)
+}
+
+#[cfg(not(unix))]
+fn listen_unix(_stub : &mut GdbStub, _path : &str) -> std::io::Result<()>
 {
-    let mut file = File::open(file_name)?;
-    let mut content : Vec<u8> = vec!();
-    let result = file.read_to_end(&mut content);
-    result.map(|_| content)
+    Err(std::io::Error::other("unix sockets are not available"))
 }
@@ -0,0 +1,116 @@
+use std::ops::Index;
+use std::sync::Arc;
+use opcode::WordSource;
+use state_hash;
+
+const PAGE_SIZE : usize = 1024;
+
+// VM memory split into pages shared between clones. A page is only copied
+// when a clone writes to it, so forking a VM costs a few reference counts
+// instead of copying the whole 32K words.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct Memory
+{
+    pages : Vec<Arc<Vec<u16>>>,
+    len : usize,
+    hash : u64,
+}
+
+impl Memory
+{
+    pub fn new(words : Vec<u16>) -> Memory
+    {
+        let len = words.len();
+        let hash =
+            words.iter()
+                .enumerate()
+                .fold(0u64, |hash, (address, &word)| hash.wrapping_add(state_hash::term(state_hash::MEMORY, address, word)));
+        let pages = words.chunks(PAGE_SIZE).map(|page| Arc::new(page.to_vec())).collect();
+        Memory { pages, len, hash }
+    }
+
+    pub fn len(&self) -> usize
+    {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool
+    {
+        self.len == 0
+    }
+
+    pub fn get(&self, address : usize) -> Option<u16>
+    {
+        if address < self.len
+        {
+            Some(self.pages[address / PAGE_SIZE][address % PAGE_SIZE])
+        }
+        else
+        {
+            None
+        }
+    }
+
+    pub fn write(&mut self, address : usize, value : u16)
+    {
+        assert!(address < self.len, "write out of memory at {}", address);
+        let page = Arc::make_mut(&mut self.pages[address / PAGE_SIZE]);
+        let old_value = page[address % PAGE_SIZE];
+        page[address % PAGE_SIZE] = value;
+        self.hash = state_hash::update(self.hash, state_hash::MEMORY, address, old_value, value);
+    }
+
+    // Extends the memory to `len` words filled with 0, never shrinks it.
+    pub fn grow(&mut self, len : usize)
+    {
+        while self.len < len
+        {
+            if self.len.is_multiple_of(PAGE_SIZE)
+            {
+                self.pages.push(Arc::new(vec!()));
+            }
+            let page = Arc::make_mut(self.pages.last_mut().unwrap());
+            let added = (PAGE_SIZE - page.len()).min(len - self.len);
+            page.resize(page.len() + added, 0);
+            for address in self.len..self.len + added
+            {
+                self.hash = self.hash.wrapping_add(state_hash::term(state_hash::MEMORY, address, 0));
+            }
+            self.len += added;
+        }
+    }
+
+    // This memory's part of the VM state hash, kept up to date by `write`.
+    pub fn state_hash(&self) -> u64
+    {
+        self.hash
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &u16>
+    {
+        self.pages.iter().flat_map(|page| page.iter())
+    }
+
+    pub fn to_vec(&self) -> Vec<u16>
+    {
+        self.iter().cloned().collect()
+    }
+}
+
+impl Index<usize> for Memory
+{
+    type Output = u16;
+
+    fn index(&self, address : usize) -> &u16
+    {
+        &self.pages[address / PAGE_SIZE][address % PAGE_SIZE]
+    }
+}
+
+impl WordSource for Memory
+{
+    fn word(&self, offset : usize) -> Option<u16>
+    {
+        self.get(offset)
+    }
+}
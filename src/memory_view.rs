@@ -0,0 +1,253 @@
+use std::fmt::Write;
+use memory::Memory;
+use symbols::parse_address;
+use vm::{VM, RunFailure};
+
+pub const COLUMNS : usize = 8;
+const PAGE_ROWS : usize = 16;
+// Search results listed before the count alone is given.
+const MAX_MATCHES_SHOWN : usize = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WordFormat
+{
+    Decimal,
+    Hex,
+    Ascii,
+}
+
+// How a string is laid out in memory: the game stores its text as a
+// length word followed by one word per character.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding
+{
+    LengthPrefixed,
+    Raw,
+}
+
+// A memory cell changed from the view.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Edit
+{
+    pub step : u64,
+    pub address : u16,
+    pub old : u16,
+    pub new : u16,
+}
+
+#[derive(Debug)]
+pub enum CommandFailure
+{
+    Usage(&'static str),
+    InvalidNumber(String),
+    WriteFailed(RunFailure),
+}
+
+const USAGE : &str =
+    "mem [dec|hex|ascii|next|prev|edits] | mem [goto] <address> | \
+     mem find <value>|raw <text>|str <text> | mem set <address> <value>...";
+
+// Interactive memory view: a page of words in the selected format, search
+// and live patching. Patches go through `VM::patch_memory`, which the code
+// watch sees like a `wmem`, and are kept in an edit log.
+#[derive(Debug, Clone)]
+pub struct MemoryView
+{
+    pub address : u16,
+    pub format : WordFormat,
+    edits : Vec<Edit>,
+}
+
+impl MemoryView
+{
+    pub fn new() -> MemoryView
+    {
+        MemoryView { address : 0, format : WordFormat::Hex, edits : vec!() }
+    }
+
+    pub fn edits(&self) -> &[Edit]
+    {
+        &self.edits
+    }
+
+    // Runs the arguments of a `mem` command and returns the text to show.
+    pub fn execute(&mut self, vm : &mut VM, arguments : &str) -> Result<String, CommandFailure>
+    {
+        let arguments = arguments.trim();
+        let (command, rest) = arguments.split_once(' ').unwrap_or((arguments, ""));
+        let rest = rest.trim();
+        match command
+        {
+            "" => (),
+            "dec" => self.format = WordFormat::Decimal,
+            "hex" => self.format = WordFormat::Hex,
+            "ascii" => self.format = WordFormat::Ascii,
+            "next" => self.scroll(vm.memory(), (PAGE_ROWS * COLUMNS) as i32),
+            "prev" => self.scroll(vm.memory(), -((PAGE_ROWS * COLUMNS) as i32)),
+            "goto" => self.address = parse_number(rest)?,
+            "edits" => return Ok(self.describe_edits()),
+            "find" => return self.find(vm.memory(), rest),
+            "set" => return self.set(vm, rest),
+            _ =>
+                match parse_address(command)
+                {
+                    Some(address) if rest.is_empty() => self.address = address,
+                    _ => return Err(CommandFailure::Usage(USAGE)),
+                },
+        }
+        Ok(self.render(vm.memory(), PAGE_ROWS))
+    }
+
+    // Moves the view by `words`, staying within memory.
+    pub fn scroll(&mut self, memory : &Memory, words : i32)
+    {
+        let last_row = memory.len().saturating_sub(1) / COLUMNS * COLUMNS;
+        let address = (self.address as i32 + words).clamp(0, last_row as i32);
+        self.address = address as u16;
+    }
+
+    // `rows` lines of `COLUMNS` words from the view address, each followed
+    // by the words read as characters.
+    pub fn render(&self, memory : &Memory, rows : usize) -> String
+    {
+        let mut text = String::new();
+        for row in 0..rows
+        {
+            let address = self.address as usize + row * COLUMNS;
+            if address >= memory.len()
+            {
+                break;
+            }
+            let words : Vec<u16> = (address..address + COLUMNS).filter_map(|a| memory.get(a)).collect();
+            let cells : Vec<String> = words.iter().map(|&word| self.cell(word)).collect();
+            let ascii : String = words.iter().map(|&word| printable(word).unwrap_or('.')).collect();
+            let _ = writeln!(text, "{:5}  {}  {}", address, cells.join(" "), ascii);
+        }
+        text
+    }
+
+    fn cell(&self, word : u16) -> String
+    {
+        match self.format
+        {
+            WordFormat::Decimal => format!("{:5}", word),
+            WordFormat::Hex => format!("{:04x}", word),
+            WordFormat::Ascii =>
+                match printable(word)
+                {
+                    Some(c) => format!("'{}'", c),
+                    None if word == 10 => "\\n ".to_owned(),
+                    None => " . ".to_owned(),
+                },
+        }
+    }
+
+    fn find(&mut self, memory : &Memory, arguments : &str) -> Result<String, CommandFailure>
+    {
+        let needle =
+            if let Some(text) = arguments.strip_prefix("raw ")
+            {
+                encode(text, Encoding::Raw)
+            }
+            else if let Some(text) = arguments.strip_prefix("str ")
+            {
+                encode(text, Encoding::LengthPrefixed)
+            }
+            else
+            {
+                vec!(parse_number(arguments)?)
+            };
+
+        let matches = search(memory, &needle);
+        if let Some(&first) = matches.first()
+        {
+            self.address = first;
+        }
+        let shown : Vec<String> = matches.iter().take(MAX_MATCHES_SHOWN).map(|address| address.to_string()).collect();
+        let more = if matches.len() > MAX_MATCHES_SHOWN { " ..." } else { "" };
+        Ok(format!("{} matches: {}{}\n", matches.len(), shown.join(" "), more))
+    }
+
+    // Writes consecutive words from `address` and logs each change.
+    fn set(&mut self, vm : &mut VM, arguments : &str) -> Result<String, CommandFailure>
+    {
+        let mut fields = arguments.split_whitespace();
+        let address = parse_number(fields.next().ok_or(CommandFailure::Usage(USAGE))?)?;
+        let values = fields.map(parse_number).collect::<Result<Vec<u16>, CommandFailure>>()?;
+        if values.is_empty()
+        {
+            return Err(CommandFailure::Usage(USAGE));
+        }
+
+        let mut text = String::new();
+        for (i, &value) in values.iter().enumerate()
+        {
+            let edit = self.patch(vm, address.wrapping_add(i as u16), value)?;
+            let _ = writeln!(text, "{}: {} -> {}", edit.address, edit.old, edit.new);
+        }
+        Ok(text)
+    }
+
+    pub fn patch(&mut self, vm : &mut VM, address : u16, value : u16) -> Result<Edit, CommandFailure>
+    {
+        let old = vm.memory().get(address as usize).unwrap_or(0);
+        vm.patch_memory(address, value).map_err(CommandFailure::WriteFailed)?;
+        let edit = Edit { step : vm.step_nb(), address, old, new : value };
+        self.edits.push(edit.clone());
+        Ok(edit)
+    }
+
+    fn describe_edits(&self) -> String
+    {
+        let mut text = String::new();
+        for edit in &self.edits
+        {
+            let _ = writeln!(text, "step {}: {}: {} -> {}", edit.step, edit.address, edit.old, edit.new);
+        }
+        text
+    }
+}
+
+impl Default for MemoryView
+{
+    fn default() -> MemoryView
+    {
+        MemoryView::new()
+    }
+}
+
+pub fn encode(text : &str, encoding : Encoding) -> Vec<u16>
+{
+    let mut words : Vec<u16> = text.chars().map(|c| c as u16).collect();
+    if encoding == Encoding::LengthPrefixed
+    {
+        words.insert(0, words.len() as u16);
+    }
+    words
+}
+
+// Addresses where `needle` starts.
+pub fn search(memory : &Memory, needle : &[u16]) -> Vec<u16>
+{
+    if needle.is_empty() || needle.len() > memory.len()
+    {
+        return vec!();
+    }
+    let words = memory.to_vec();
+    words
+        .windows(needle.len())
+        .enumerate()
+        .filter(|&(_, window)| window == needle)
+        .map(|(address, _)| address as u16)
+        .collect()
+}
+
+fn printable(word : u16) -> Option<char>
+{
+    if (0x20..0x7f).contains(&word) { Some(word as u8 as char) } else { None }
+}
+
+fn parse_number(text : &str) -> Result<u16, CommandFailure>
+{
+    parse_address(text).ok_or_else(|| CommandFailure::InvalidNumber(text.to_owned()))
+}
@@ -1,23 +1,23 @@
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub struct SetRegister
 {
     pub register : u16,
     pub value : u16,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub struct Push
 {
     pub value : u16,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub struct Pop
 {
     pub value : u16,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub struct IsEqual
 {
     pub cell_result : u16,
@@ -25,7 +25,7 @@ pub struct IsEqual
     pub second_operand : u16,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub struct IsGreaterThan
 {
     pub cell_result : u16,
@@ -33,27 +33,27 @@ pub struct IsGreaterThan
     pub second_operand : u16,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub struct Jump
 {
     pub value : u16,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub struct JumpNotZero
 {
     pub value: u16,
     pub jump_location : u16,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub struct JumpZero
 {
     pub value: u16,
     pub jump_location : u16,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub struct Add
 {
     pub cell_result : u16,
@@ -61,7 +61,7 @@ pub struct Add
     pub second_operand : u16,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub struct Multiply
 {
     pub cell_result : u16,
@@ -69,7 +69,7 @@ pub struct Multiply
     pub second_operand : u16,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub struct Modulo
 {
     pub cell_result : u16,
@@ -77,7 +77,7 @@ pub struct Modulo
     pub second_operand : u16,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub struct And
 {
     pub cell_result : u16,
@@ -85,7 +85,7 @@ pub struct And
     pub second_operand : u16,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub struct Or
 {
     pub cell_result : u16,
@@ -93,46 +93,46 @@ pub struct Or
     pub second_operand : u16,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub struct Not
 {
     pub cell_result : u16,
     pub operand : u16,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub struct ReadMemory
 {
     pub memory_address_to_read : u16,
     pub cell_result : u16,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub struct WriteMemory
 {
     pub value : u16,
     pub memory_address_to_write_to : u16,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub struct Call
 {
     pub value : u16,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub struct Out
 {
     pub value : u16,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub struct In
 {
     pub value : u16,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub enum OpCode
 {
     Halt,
@@ -159,7 +159,25 @@ pub enum OpCode
     Noop,
 }
 
-#[derive(Debug)]
+impl OpCode
+{
+    // Number of words the instruction takes, op code included.
+    pub fn size(&self) -> u16
+    {
+        match *self
+        {
+            OpCode::Halt | OpCode::Return | OpCode::Noop => 1,
+            OpCode::Push(_) | OpCode::Pop(_) | OpCode::Jump(_) |
+            OpCode::Call(_) | OpCode::Out(_) | OpCode::In(_) => 2,
+            OpCode::SetRegister(_) | OpCode::JumpNotZero(_) | OpCode::JumpZero(_) |
+            OpCode::Not(_) | OpCode::ReadMemory(_) | OpCode::WriteMemory(_) => 3,
+            OpCode::IsEqual(_) | OpCode::IsGreaterThan(_) | OpCode::Add(_) |
+            OpCode::Multiply(_) | OpCode::And(_) | OpCode::Or(_) | OpCode::Modulo(_) => 4,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
 pub enum ReadOpCodeFailure
 {
     NotEnoughMemory,
@@ -168,19 +186,31 @@ pub enum ReadOpCodeFailure
     InvalidOperandValue
 }
 
-fn read_mem_as_u16_le(mem: &[u16], offset : u16) -> Option<u16>
+// Anything the decoder can read instruction words from.
+pub trait WordSource
+{
+    fn word(&self, offset : usize) -> Option<u16>;
+}
+
+impl WordSource for [u16]
 {
-    match mem.get(offset as usize) 
+    fn word(&self, offset : usize) -> Option<u16>
     {
-        None => None,
-        Some(x) => Some(*x)
+        self.get(offset).copied()
     }
 }
 
-pub fn read_memory_to_op_code(mem : &[u16], offset : u16) -> 
+// Operand offsets are computed in usize so that decoding at the end of the
+// address space reports missing words instead of overflowing.
+fn read_mem_as_u16_le<M : WordSource + ?Sized>(mem : &M, offset : usize) -> Option<u16>
+{
+    mem.word(offset)
+}
+
+pub fn read_memory_to_op_code<M : WordSource + ?Sized>(mem : &M, offset : u16) -> 
     Result<OpCode, ReadOpCodeFailure>
 {
-    let first_op_code_result = read_mem_as_u16_le(mem, offset);
+    let first_op_code_result = read_mem_as_u16_le(mem, offset as usize);
     if first_op_code_result.is_none()
     {
         return Err(ReadOpCodeFailure::NotEnoughMemory);
@@ -219,17 +249,17 @@ pub fn read_memory_to_op_code(mem : &[u16], offset : u16) ->
         21 => Ok(OpCode::Noop),
         x => 
         {
-            println!("Invalid OpCode for value {}", x);
+            eprintln!("Invalid OpCode for value {}", x);
             Err(ReadOpCodeFailure::InvalidOpCode)
         },
     }  
 }
 
-fn handle_set_register_case(mem : &[u16], offset : u16) -> 
+fn handle_set_register_case<M : WordSource + ?Sized>(mem : &M, offset : u16) -> 
     Result<OpCode, ReadOpCodeFailure>
 {
-    let register_result = read_mem_as_u16_le(mem, (offset + 1));
-    let value_result = read_mem_as_u16_le(mem, (offset + 2));
+    let register_result = read_mem_as_u16_le(mem, offset as usize + 1);
+    let value_result = read_mem_as_u16_le(mem, offset as usize + 2);
     let mut is_ok = register_result.is_some() && value_result.is_some();
     if !is_ok
     {
@@ -246,14 +276,14 @@ fn handle_set_register_case(mem : &[u16], offset : u16) ->
     {
         return Err(ReadOpCodeFailure::InvalidOperandValue);
     }
-    let st = SetRegister {register : register, value : value };
+    let st = SetRegister {register, value };
     Ok(OpCode::SetRegister(st))
 }
 
-fn handle_push_case(mem : &[u16], offset : u16) -> 
+fn handle_push_case<M : WordSource + ?Sized>(mem : &M, offset : u16) -> 
     Result<OpCode, ReadOpCodeFailure>
 {
-    let value_result = read_mem_as_u16_le(mem, (offset + 1));
+    let value_result = read_mem_as_u16_le(mem, offset as usize + 1);
     let mut is_ok = value_result.is_some();
     if !is_ok
     {
@@ -265,14 +295,14 @@ fn handle_push_case(mem : &[u16], offset : u16) ->
     {
         return Err(ReadOpCodeFailure::InvalidOperandValue);
     }
-    let push = Push {value : value};
+    let push = Push {value};
     Ok(OpCode::Push(push))
 }
 
-fn handle_pop_case(mem : &[u16], offset : u16) -> 
+fn handle_pop_case<M : WordSource + ?Sized>(mem : &M, offset : u16) -> 
     Result<OpCode, ReadOpCodeFailure>
 {
-    let value_result = read_mem_as_u16_le(mem, (offset + 1));
+    let value_result = read_mem_as_u16_le(mem, offset as usize + 1);
     let mut is_ok = value_result.is_some();
     if !is_ok
     {
@@ -284,16 +314,16 @@ fn handle_pop_case(mem : &[u16], offset : u16) ->
     {
         return Err(ReadOpCodeFailure::InvalidOperandValue);
     }
-    let pop = Pop {value : value};
+    let pop = Pop {value};
     Ok(OpCode::Pop(pop))
 }
 
-fn handle_is_equal_case(mem : &[u16], offset : u16) -> 
+fn handle_is_equal_case<M : WordSource + ?Sized>(mem : &M, offset : u16) -> 
     Result<OpCode, ReadOpCodeFailure>
 {
-    let cell_result = read_mem_as_u16_le(mem, (offset + 1));
-    let first_operand_result = read_mem_as_u16_le(mem, (offset + 2));
-    let second_operand_result = read_mem_as_u16_le(mem, (offset + 3));
+    let cell_result = read_mem_as_u16_le(mem, offset as usize + 1);
+    let first_operand_result = read_mem_as_u16_le(mem, offset as usize + 2);
+    let second_operand_result = read_mem_as_u16_le(mem, offset as usize + 3);
 
     let mut is_ok = 
         cell_result.is_some() && 
@@ -321,18 +351,18 @@ fn handle_is_equal_case(mem : &[u16], offset : u16) ->
         IsEqual 
         {
             cell_result : cell, 
-            first_operand : first_operand, 
-            second_operand : second_operand 
+            first_operand, 
+            second_operand 
         };
     Ok(OpCode::IsEqual(is_equal))
 }
 
-fn handle_is_greater_case(mem : &[u16], offset : u16) -> 
+fn handle_is_greater_case<M : WordSource + ?Sized>(mem : &M, offset : u16) -> 
     Result<OpCode, ReadOpCodeFailure>
 {
-    let cell_result = read_mem_as_u16_le(mem, offset + 1);
-    let first_operand_result = read_mem_as_u16_le(mem, offset + 2);
-    let second_operand_result = read_mem_as_u16_le(mem, offset + 3);
+    let cell_result = read_mem_as_u16_le(mem, offset as usize + 1);
+    let first_operand_result = read_mem_as_u16_le(mem, offset as usize + 2);
+    let second_operand_result = read_mem_as_u16_le(mem, offset as usize + 3);
 
     let mut is_ok = 
         cell_result.is_some() && 
@@ -360,37 +390,37 @@ fn handle_is_greater_case(mem : &[u16], offset : u16) ->
         IsGreaterThan 
         {
             cell_result : cell, 
-            first_operand : first_operand, 
-            second_operand : second_operand 
+            first_operand, 
+            second_operand 
         };
 
     Ok(OpCode::IsGreaterThan(is_greater))
 }
 
-fn handle_jump_case(mem : &[u16], offset : u16) -> 
+fn handle_jump_case<M : WordSource + ?Sized>(mem : &M, offset : u16) -> 
     Result<OpCode, ReadOpCodeFailure>
 {
-    let value_result = read_mem_as_u16_le(mem, offset + 1);
+    let value_result = read_mem_as_u16_le(mem, offset as usize + 1);
     let mut is_ok = value_result.is_some();
     if !is_ok
     {
         return Err(ReadOpCodeFailure::NotEnoughMemory);
     }
     let value = value_result.unwrap();
-    is_ok = check_number(value).is_literal_value();
+    is_ok = check_number(value).is_valid_number();
     if !is_ok
     {
         return Err(ReadOpCodeFailure::InvalidOperandValue);
     }
-    let jump = Jump {value : value};
+    let jump = Jump {value};
     Ok(OpCode::Jump(jump))
 }
 
-fn handle_jump_not_zero_case(mem : &[u16], offset : u16) -> 
+fn handle_jump_not_zero_case<M : WordSource + ?Sized>(mem : &M, offset : u16) -> 
     Result<OpCode, ReadOpCodeFailure>
 {
-    let value_result = read_mem_as_u16_le(mem, offset + 1);
-    let jump_location_result = read_mem_as_u16_le(mem, offset + 2);
+    let value_result = read_mem_as_u16_le(mem, offset as usize + 1);
+    let jump_location_result = read_mem_as_u16_le(mem, offset as usize + 2);
     let mut is_ok = value_result.is_some() && jump_location_result.is_some();
     if !is_ok
     {
@@ -401,21 +431,21 @@ fn handle_jump_not_zero_case(mem : &[u16], offset : u16) ->
 
     is_ok = 
         check_number(value).is_valid_number() && 
-        check_number(jump_location).is_literal_value();
+        check_number(jump_location).is_valid_number();
     
     if !is_ok
     {
         return Err(ReadOpCodeFailure::InvalidOperandValue);
     }
-    let jump_not_zero = JumpNotZero {value : value, jump_location : jump_location};
+    let jump_not_zero = JumpNotZero {value, jump_location};
     Ok(OpCode::JumpNotZero(jump_not_zero))
 }
 
-fn handle_jump_zero_case(mem: &[u16], offset : u16) -> 
+fn handle_jump_zero_case<M : WordSource + ?Sized>(mem : &M, offset : u16) -> 
     Result<OpCode, ReadOpCodeFailure>
 {
-    let value_result = read_mem_as_u16_le(mem, offset + 1);
-    let jump_location_result = read_mem_as_u16_le(mem, offset + 2);
+    let value_result = read_mem_as_u16_le(mem, offset as usize + 1);
+    let jump_location_result = read_mem_as_u16_le(mem, offset as usize + 2);
     let mut is_ok = value_result.is_some() && jump_location_result.is_some();
     if !is_ok
     {
@@ -426,22 +456,22 @@ fn handle_jump_zero_case(mem: &[u16], offset : u16) ->
 
     is_ok = 
         check_number(value).is_valid_number() && 
-        check_number(jump_location).is_literal_value();
+        check_number(jump_location).is_valid_number();
     
     if !is_ok
     {
         return Err(ReadOpCodeFailure::InvalidOperandValue);
     }
-    let jump_zero = JumpZero {value : value, jump_location : jump_location};
+    let jump_zero = JumpZero {value, jump_location};
     Ok(OpCode::JumpZero(jump_zero))
 }
 
-fn handle_add_case(mem: &[u16], offset : u16) -> 
+fn handle_add_case<M : WordSource + ?Sized>(mem : &M, offset : u16) -> 
     Result<OpCode, ReadOpCodeFailure>
 {
-    let cell_result = read_mem_as_u16_le(mem, offset + 1);
-    let first_operand_result = read_mem_as_u16_le(mem, offset + 2);
-    let second_operand_result = read_mem_as_u16_le(mem, offset + 3);
+    let cell_result = read_mem_as_u16_le(mem, offset as usize + 1);
+    let first_operand_result = read_mem_as_u16_le(mem, offset as usize + 2);
+    let second_operand_result = read_mem_as_u16_le(mem, offset as usize + 3);
 
     let mut is_ok = 
         cell_result.is_some() && 
@@ -469,19 +499,19 @@ fn handle_add_case(mem: &[u16], offset : u16) ->
         Add 
         {
             cell_result : cell, 
-            first_operand : first_operand, 
-            second_operand : second_operand 
+            first_operand, 
+            second_operand 
         };
 
     Ok(OpCode::Add(add))
 }
 
-fn handle_multiply_case(mem: &[u16], offset : u16) -> 
+fn handle_multiply_case<M : WordSource + ?Sized>(mem : &M, offset : u16) -> 
     Result<OpCode, ReadOpCodeFailure>
 {
-    let cell_result = read_mem_as_u16_le(mem, offset + 1);
-    let first_operand_result = read_mem_as_u16_le(mem, offset + 2);
-    let second_operand_result = read_mem_as_u16_le(mem, offset + 3);
+    let cell_result = read_mem_as_u16_le(mem, offset as usize + 1);
+    let first_operand_result = read_mem_as_u16_le(mem, offset as usize + 2);
+    let second_operand_result = read_mem_as_u16_le(mem, offset as usize + 3);
 
     let mut is_ok = 
         cell_result.is_some() && 
@@ -510,19 +540,19 @@ fn handle_multiply_case(mem: &[u16], offset : u16) ->
         Multiply 
         {
             cell_result : cell, 
-            first_operand : first_operand, 
-            second_operand : second_operand 
+            first_operand, 
+            second_operand 
         };
 
     Ok(OpCode::Multiply(mult))
 }
 
-fn handle_modulo_case(mem: &[u16], offset : u16) -> 
+fn handle_modulo_case<M : WordSource + ?Sized>(mem : &M, offset : u16) -> 
     Result<OpCode, ReadOpCodeFailure>
 {
-    let cell_result = read_mem_as_u16_le(mem, offset + 1);
-    let first_operand_result = read_mem_as_u16_le(mem, offset + 2);
-    let second_operand_result = read_mem_as_u16_le(mem, offset + 3);
+    let cell_result = read_mem_as_u16_le(mem, offset as usize + 1);
+    let first_operand_result = read_mem_as_u16_le(mem, offset as usize + 2);
+    let second_operand_result = read_mem_as_u16_le(mem, offset as usize + 3);
 
     let mut is_ok = 
         cell_result.is_some() && 
@@ -552,19 +582,19 @@ fn handle_modulo_case(mem: &[u16], offset : u16) ->
         Modulo 
         {
             cell_result : cell, 
-            first_operand : first_operand, 
-            second_operand : second_operand 
+            first_operand, 
+            second_operand 
         };
 
     Ok(OpCode::Modulo(modulo))
 }
 
-fn handle_and_case(mem: &[u16], offset : u16) -> 
+fn handle_and_case<M : WordSource + ?Sized>(mem : &M, offset : u16) -> 
     Result<OpCode, ReadOpCodeFailure>
 {
-    let cell_result = read_mem_as_u16_le(mem, offset + 1);
-    let first_operand_result = read_mem_as_u16_le(mem, offset + 2);
-    let second_operand_result = read_mem_as_u16_le(mem, offset + 3);
+    let cell_result = read_mem_as_u16_le(mem, offset as usize + 1);
+    let first_operand_result = read_mem_as_u16_le(mem, offset as usize + 2);
+    let second_operand_result = read_mem_as_u16_le(mem, offset as usize + 3);
 
     let mut is_ok = 
         cell_result.is_some() && 
@@ -594,19 +624,19 @@ fn handle_and_case(mem: &[u16], offset : u16) ->
         And 
         {
             cell_result : cell, 
-            first_operand : first_operand, 
-            second_operand : second_operand 
+            first_operand, 
+            second_operand 
         };
 
     Ok(OpCode::And(and))
 }
 
-fn handle_or_case(mem: &[u16], offset : u16) -> 
+fn handle_or_case<M : WordSource + ?Sized>(mem : &M, offset : u16) -> 
     Result<OpCode, ReadOpCodeFailure>
 {
-    let cell_result = read_mem_as_u16_le(mem, offset + 1);
-    let first_operand_result = read_mem_as_u16_le(mem, offset + 2);
-    let second_operand_result = read_mem_as_u16_le(mem, offset + 3);
+    let cell_result = read_mem_as_u16_le(mem, offset as usize + 1);
+    let first_operand_result = read_mem_as_u16_le(mem, offset as usize + 2);
+    let second_operand_result = read_mem_as_u16_le(mem, offset as usize + 3);
 
     let mut is_ok = 
         cell_result.is_some() && 
@@ -636,17 +666,17 @@ fn handle_or_case(mem: &[u16], offset : u16) ->
         Or 
         {
             cell_result : cell, 
-            first_operand : first_operand, 
-            second_operand : second_operand 
+            first_operand, 
+            second_operand 
         };
     Ok(OpCode::Or(or))
 }
 
-fn handle_not_case(mem: &[u16], offset : u16) -> 
+fn handle_not_case<M : WordSource + ?Sized>(mem : &M, offset : u16) -> 
     Result<OpCode, ReadOpCodeFailure>
 {
-    let cell_result = read_mem_as_u16_le(mem, offset + 1);
-    let operand_result = read_mem_as_u16_le(mem, offset + 2);
+    let cell_result = read_mem_as_u16_le(mem, offset as usize + 1);
+    let operand_result = read_mem_as_u16_le(mem, offset as usize + 2);
 
     let mut is_ok = 
         cell_result.is_some() && 
@@ -671,17 +701,17 @@ fn handle_not_case(mem: &[u16], offset : u16) ->
         Not 
         {
             cell_result : cell, 
-            operand : operand
+            operand
         };
         
     Ok(OpCode::Not(not))
 }
 
-fn handle_read_memory_case(mem: &[u16], offset : u16) -> 
+fn handle_read_memory_case<M : WordSource + ?Sized>(mem : &M, offset : u16) -> 
     Result<OpCode, ReadOpCodeFailure>
 {
-    let cell_result = read_mem_as_u16_le(mem, offset + 1);
-    let memory_address_to_read_result = read_mem_as_u16_le(mem, offset + 2);
+    let cell_result = read_mem_as_u16_le(mem, offset as usize + 1);
+    let memory_address_to_read_result = read_mem_as_u16_le(mem, offset as usize + 2);
     
     let mut is_ok = 
         cell_result.is_some() &&
@@ -708,16 +738,16 @@ fn handle_read_memory_case(mem: &[u16], offset : u16) ->
         ReadMemory 
         {
             cell_result : cell, 
-            memory_address_to_read : memory_address_to_read
+            memory_address_to_read
         };
     Ok(OpCode::ReadMemory(read_memory))
 }
 
-fn handle_write_memory_case(mem: &[u16], offset : u16) -> 
+fn handle_write_memory_case<M : WordSource + ?Sized>(mem : &M, offset : u16) -> 
     Result<OpCode, ReadOpCodeFailure>
 {
-    let memory_address_to_write_to_result = read_mem_as_u16_le(mem, offset + 1);
-    let value_result = read_mem_as_u16_le(mem, offset + 2);
+    let memory_address_to_write_to_result = read_mem_as_u16_le(mem, offset as usize + 1);
+    let value_result = read_mem_as_u16_le(mem, offset as usize + 2);
     
     let mut is_ok = 
         value_result.is_some() &&
@@ -742,16 +772,16 @@ fn handle_write_memory_case(mem: &[u16], offset : u16) ->
     let write_memory = 
         WriteMemory 
         {
-            value : value, 
-            memory_address_to_write_to : memory_address_to_write_to
+            value, 
+            memory_address_to_write_to
         };
     Ok(OpCode::WriteMemory(write_memory))
 }
 
-fn handle_call_case(mem: &[u16], offset : u16) -> 
+fn handle_call_case<M : WordSource + ?Sized>(mem : &M, offset : u16) -> 
     Result<OpCode, ReadOpCodeFailure>
 {
-    let value_result = read_mem_as_u16_le(mem, offset + 1);
+    let value_result = read_mem_as_u16_le(mem, offset as usize + 1);
     let mut is_ok = value_result.is_some();
     if !is_ok
     {
@@ -763,14 +793,14 @@ fn handle_call_case(mem: &[u16], offset : u16) ->
     {
         return Err(ReadOpCodeFailure::InvalidOperandValue);
     }
-    let call = Call {value : value};
+    let call = Call {value};
     Ok(OpCode::Call(call))
 }
 
-fn handle_out_case(mem: &[u16], offset : u16) -> 
+fn handle_out_case<M : WordSource + ?Sized>(mem : &M, offset : u16) -> 
     Result<OpCode, ReadOpCodeFailure>
 {
-    let value_result = read_mem_as_u16_le(mem, offset + 1);
+    let value_result = read_mem_as_u16_le(mem, offset as usize + 1);
     let mut is_ok = value_result.is_some();
     if !is_ok
     {
@@ -782,14 +812,14 @@ fn handle_out_case(mem: &[u16], offset : u16) ->
     {
         return Err(ReadOpCodeFailure::InvalidOperandValue);
     }
-    let out = Out {value : value};
+    let out = Out {value};
     Ok(OpCode::Out(out))
 }
 
-fn handle_in_case(mem: &[u16], offset : u16) -> 
+fn handle_in_case<M : WordSource + ?Sized>(mem : &M, offset : u16) -> 
     Result<OpCode, ReadOpCodeFailure>
 {
-    let value_result = read_mem_as_u16_le(mem, offset + 1);
+    let value_result = read_mem_as_u16_le(mem, offset as usize + 1);
     let mut is_ok = value_result.is_some();
     if !is_ok
     {
@@ -801,7 +831,7 @@ fn handle_in_case(mem: &[u16], offset : u16) ->
     {
         return Err(ReadOpCodeFailure::InvalidOperandValue);
     }
-    let in_ = In {value : value};
+    let in_ = In {value};
     Ok(OpCode::In(in_))
 }
 
@@ -819,38 +849,22 @@ impl ParsedNumber
 {
     pub fn is_literal_value(&self) -> bool
     {
-        match *self
-        {
-            ParsedNumber::LiteralValue(_) => true,
-            _ => false
-        }
+        matches!(*self, ParsedNumber::LiteralValue(_))
     }
 
     pub fn is_register(&self) -> bool
     {
-        match *self
-        {
-            ParsedNumber::Register(_) => true,
-            _ => false
-        }
+        matches!(*self, ParsedNumber::Register(_))
     }
 
     pub fn is_invalid_number(&self) -> bool
     {
-        match *self
-        {
-            ParsedNumber::InvalidNumber => true,
-            _ => false
-        }
+        matches!(*self, ParsedNumber::InvalidNumber)
     }
 
     pub fn is_valid_number(&self) -> bool
     {
-        match *self
-        {
-            ParsedNumber::InvalidNumber => false,
-            _ => true
-        }
+        !self.is_invalid_number()
     }
 }
 
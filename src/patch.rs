@@ -0,0 +1,181 @@
+use std::fmt::Write;
+use std::fs;
+use std::io;
+use memory::Memory;
+use run_until::{StopConditions, StopReason};
+use symbols::parse_address;
+use vm::{VM, RunFailure};
+
+// A set of word changes for one program image, read from a text file:
+//
+//     # comment
+//     image 1f2e3d4c5b6a7988
+//     step 2000000
+//     5489 17 21
+//
+// `image` is the checksum of the image the patch was made for and is
+// required. `step` is optional, without it the patch is applied at load
+// time. The other lines are `address old new`, in decimal or with a 0x
+// prefix. A patch is only applied when every old word matches.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Patch
+{
+    pub image_checksum : u64,
+    pub step : Option<u64>,
+    pub words : Vec<PatchWord>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PatchWord
+{
+    pub address : u16,
+    pub old : u16,
+    pub new : u16,
+}
+
+#[derive(Debug)]
+pub enum PatchFailure
+{
+    CannotRead(io::Error),
+    InvalidLine(usize, String),
+    MissingImageChecksum,
+    ImageMismatch { expected : u64, actual : u64 },
+    OldWordMismatch { address : u16, expected : u16, actual : Option<u16> },
+    WriteFailed(RunFailure),
+}
+
+// Checksum of a program image as loaded, before it runs.
+pub fn image_checksum(words : &[u16]) -> u64
+{
+    Memory::new(words.to_vec()).state_hash()
+}
+
+impl Patch
+{
+    pub fn load(file_name : &str) -> Result<Patch, PatchFailure>
+    {
+        let text = fs::read_to_string(file_name).map_err(PatchFailure::CannotRead)?;
+        Patch::parse(&text)
+    }
+
+    pub fn parse(text : &str) -> Result<Patch, PatchFailure>
+    {
+        let mut image_checksum = None;
+        let mut step = None;
+        let mut words = vec!();
+        for (i, line) in text.lines().enumerate()
+        {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty()
+            {
+                continue;
+            }
+            let invalid_line = || PatchFailure::InvalidLine(i + 1, line.to_owned());
+            let fields : Vec<&str> = line.split_whitespace().collect();
+            match fields[..]
+            {
+                ["image", checksum] =>
+                    image_checksum = Some(u64::from_str_radix(checksum, 16).map_err(|_| invalid_line())?),
+                ["step", n] => step = Some(n.parse().map_err(|_| invalid_line())?),
+                [address, old, new] =>
+                {
+                    match (parse_address(address), parse_address(old), parse_address(new))
+                    {
+                        (Some(address), Some(old), Some(new)) => words.push(PatchWord { address, old, new }),
+                        _ => return Err(invalid_line()),
+                    }
+                },
+                _ => return Err(invalid_line()),
+            }
+        }
+
+        match image_checksum
+        {
+            None => Err(PatchFailure::MissingImageChecksum),
+            Some(image_checksum) => Ok(Patch { image_checksum, step, words }),
+        }
+    }
+
+    pub fn to_text(&self) -> String
+    {
+        let mut text = format!("image {:016x}\n", self.image_checksum);
+        if let Some(step) = self.step
+        {
+            let _ = writeln!(text, "step {}", step);
+        }
+        for word in &self.words
+        {
+            let _ = writeln!(text, "{} {} {}", word.address, word.old, word.new);
+        }
+        text
+    }
+
+    // Refuses images other than the one the patch was made for.
+    pub fn check_image(&self, image : &[u16]) -> Result<(), PatchFailure>
+    {
+        let actual = image_checksum(image);
+        if actual != self.image_checksum
+        {
+            return Err(PatchFailure::ImageMismatch { expected : self.image_checksum, actual });
+        }
+        Ok(())
+    }
+
+    // Writes the new words once all old words are found in memory, so a
+    // patch is applied entirely or not at all.
+    pub fn apply(&self, vm : &mut VM) -> Result<(), PatchFailure>
+    {
+        for word in &self.words
+        {
+            let actual = vm.memory().get(word.address as usize);
+            if actual != Some(word.old)
+            {
+                return Err(PatchFailure::OldWordMismatch { address : word.address, expected : word.old, actual });
+            }
+        }
+        for word in &self.words
+        {
+            vm.write_memory(word.address, word.new).map_err(PatchFailure::WriteFailed)?;
+        }
+        Ok(())
+    }
+}
+
+// Checks `patches` against `image`, applies those without a step to `vm`
+// and returns the others ordered by step, for `run_patched`.
+pub fn apply_at_load(vm : &mut VM, image : &[u16], patches : Vec<Patch>) -> Result<Vec<Patch>, PatchFailure>
+{
+    let mut scheduled = vec!();
+    for patch in patches
+    {
+        patch.check_image(image)?;
+        match patch.step
+        {
+            None => patch.apply(vm)?,
+            Some(_) => scheduled.push(patch),
+        }
+    }
+    scheduled.sort_by_key(|patch| patch.step);
+    Ok(scheduled)
+}
+
+// Runs `vm` until it stops, applying each scheduled patch once the step it
+// names is reached. Patches whose step is already past are applied right
+// away.
+pub fn run_patched(vm : &mut VM, scheduled : &[Patch]) -> Result<StopReason, PatchFailure>
+{
+    for patch in scheduled
+    {
+        let step = patch.step.unwrap_or(0);
+        if vm.step_nb() < step
+        {
+            match vm.run(&StopConditions::new().max_steps(step - vm.step_nb()))
+            {
+                StopReason::StepBudgetExhausted => (),
+                reason => return Ok(reason),
+            }
+        }
+        patch.apply(vm)?;
+    }
+    Ok(vm.run(&StopConditions::new()))
+}
@@ -0,0 +1,121 @@
+use std::collections::VecDeque;
+use explorer::{Explorer, ExploreFailure, WorldGraph};
+use vm::VM;
+
+#[derive(Debug, Clone)]
+pub enum Target
+{
+    Room(String),
+    RoomId(usize),
+    Item(String),
+}
+
+#[derive(Debug)]
+pub enum PlanFailure
+{
+    CannotIdentifyCurrentRoom(ExploreFailure),
+    CurrentRoomNotInGraph,
+    UnknownTarget,
+    Unreachable,
+}
+
+// Computes the shortest list of commands that takes the game from the
+// state of `vm` to `target`. Reaching an item means walking to the nearest
+// room holding it and taking it.
+pub fn plan(graph : &WorldGraph, vm : &VM, target : &Target) -> Result<Vec<String>, PlanFailure>
+{
+    let (view, fingerprint) =
+        Explorer::new()
+            .identify(vm)
+            .map_err(PlanFailure::CannotIdentifyCurrentRoom)?;
+    let from =
+        graph
+            .find_room(&view, fingerprint)
+            .ok_or(PlanFailure::CurrentRoomNotInGraph)?;
+    plan_from(graph, from, target)
+}
+
+pub fn plan_from(graph : &WorldGraph, from : usize, target : &Target) -> Result<Vec<String>, PlanFailure>
+{
+    let destinations : Vec<usize> =
+        match *target
+        {
+            Target::Room(ref name) =>
+                graph.rooms.iter()
+                    .filter(|room| room.name.eq_ignore_ascii_case(name))
+                    .map(|room| room.id)
+                    .collect(),
+            Target::RoomId(id) =>
+                graph.rooms.iter()
+                    .filter(|room| room.id == id)
+                    .map(|room| room.id)
+                    .collect(),
+            Target::Item(ref item) =>
+                graph.rooms.iter()
+                    .filter(|room| room.items.iter().any(|i| i.eq_ignore_ascii_case(item)))
+                    .map(|room| room.id)
+                    .collect(),
+        };
+
+    if destinations.is_empty()
+    {
+        return Err(PlanFailure::UnknownTarget);
+    }
+
+    let mut commands = shortest_path(graph, from, &destinations).ok_or(PlanFailure::Unreachable)?;
+    if let Target::Item(ref item) = *target
+    {
+        commands.push(format!("take {}", item));
+    }
+    Ok(commands)
+}
+
+// Breadth first search from `from` to the closest of `destinations`,
+// returning the directions to walk.
+pub fn shortest_path(graph : &WorldGraph, from : usize, destinations : &[usize]) -> Option<Vec<String>>
+{
+    let mut previous : Vec<Option<(usize, &str)>> = vec!(None; graph.rooms.len());
+    let mut visited = vec!(false; graph.rooms.len());
+    let mut to_visit = VecDeque::new();
+
+    visited[from] = true;
+    to_visit.push_back(from);
+
+    while let Some(room) = to_visit.pop_front()
+    {
+        if destinations.contains(&room)
+        {
+            let mut commands = vec!();
+            let mut current = room;
+            while let Some((before, direction)) = previous[current]
+            {
+                commands.push(direction.to_owned());
+                current = before;
+            }
+            commands.reverse();
+            return Some(commands);
+        }
+
+        for passage in graph.passages.iter().filter(|p| p.from == room)
+        {
+            if !visited[passage.to]
+            {
+                visited[passage.to] = true;
+                previous[passage.to] = Some((room, &passage.direction));
+                to_visit.push_back(passage.to);
+            }
+        }
+    }
+    None
+}
+
+pub fn to_script(commands : &[String]) -> String
+{
+    let mut script = String::new();
+    for command in commands
+    {
+        script.push_str(command);
+        script.push('\n');
+    }
+    script
+}
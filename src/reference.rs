@@ -0,0 +1,468 @@
+use std::collections::VecDeque;
+use conformance::Conformance;
+use console::{Input, Output};
+use run_until::{StopConditions, StopReason};
+use vm::VM;
+
+const MEMORY_SIZE : usize = 32768;
+const MODULO : u32 = 32768;
+
+// Straight transcription of arch-spec with no decoding step, cache or
+// shortcut, used to cross-check `VM`. Where the spec is silent it stops with
+// a fault rather than guessing. It behaves like `VM` with
+// `Conformance::strict()`.
+#[derive(Clone)]
+pub struct ReferenceVm
+{
+    pub memory : Vec<u16>,
+    pub registers : [u16; 8],
+    pub stack : Vec<u16>,
+    pub program_counter : u16,
+    pub output : String,
+    input : VecDeque<u8>,
+    // Address written by the last instruction.
+    last_write : Option<u16>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReferenceStop
+{
+    Halt,
+    InputNeeded,
+    Fault(String),
+}
+
+// How a single step ended, common to both interpreters.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StepEnd
+{
+    Running,
+    Halted,
+    InputNeeded,
+    Faulted,
+}
+
+#[derive(Debug, Clone)]
+pub struct Divergence
+{
+    // Number of instructions both ran before the one that diverged.
+    pub step : u64,
+    pub program_counter : u16,
+    pub difference : String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LockstepEnd
+{
+    StepBudgetExhausted,
+    Stopped(StepEnd),
+}
+
+impl ReferenceVm
+{
+    pub fn new(program : &[u16]) -> ReferenceVm
+    {
+        let mut memory = program.to_vec();
+        memory.resize(MEMORY_SIZE.max(program.len()), 0);
+        ReferenceVm
+        {
+            memory,
+            registers : [0; 8],
+            stack : vec!(),
+            program_counter : 0,
+            output : String::new(),
+            input : VecDeque::new(),
+            last_write : None,
+        }
+    }
+
+    pub fn push_input_line(&mut self, line : &str)
+    {
+        self.input.extend(line.bytes());
+        self.input.push_back(b'\n');
+    }
+
+    fn fault<T>(&self, message : &str) -> Result<T, ReferenceStop>
+    {
+        Err(ReferenceStop::Fault(format!("{} at {}", message, self.program_counter)))
+    }
+
+    // The `index`th word of the current instruction.
+    fn word(&self, index : usize) -> Result<u16, ReferenceStop>
+    {
+        let address = self.program_counter as usize + index;
+        if address >= MEMORY_SIZE
+        {
+            return self.fault("instruction past the end of memory");
+        }
+        Ok(self.memory[address])
+    }
+
+    // "numbers 0..32767 mean a literal value, numbers 32768..32775 instead
+    // mean registers 0..7, numbers 32776..65535 are invalid"
+    fn value(&self, index : usize) -> Result<u16, ReferenceStop>
+    {
+        match self.word(index)?
+        {
+            word @ 0..=32767 => Ok(word),
+            word @ 32768..=32775 => Ok(self.registers[(word - 32768) as usize]),
+            _ => self.fault("invalid number"),
+        }
+    }
+
+    fn register(&self, index : usize) -> Result<usize, ReferenceStop>
+    {
+        match self.word(index)?
+        {
+            word @ 32768..=32775 => Ok((word - 32768) as usize),
+            _ => self.fault("not a register"),
+        }
+    }
+
+    fn address(&self, address : u16) -> Result<usize, ReferenceStop>
+    {
+        if address as usize >= MEMORY_SIZE
+        {
+            return self.fault("address out of memory");
+        }
+        Ok(address as usize)
+    }
+
+    pub fn step(&mut self) -> Result<(), ReferenceStop>
+    {
+        self.last_write = None;
+        let pc = self.program_counter;
+        match self.word(0)?
+        {
+            // halt: stop execution and terminate the program
+            0 => return Err(ReferenceStop::Halt),
+            // set: set register <a> to the value of <b>
+            1 =>
+            {
+                let a = self.register(1)?;
+                self.registers[a] = self.value(2)?;
+                self.program_counter = pc + 3;
+            },
+            // push: push <a> onto the stack
+            2 =>
+            {
+                let a = self.value(1)?;
+                self.stack.push(a);
+                self.program_counter = pc + 2;
+            },
+            // pop: remove the top element from the stack and write it into
+            // <a>; empty stack = error
+            3 =>
+            {
+                let a = self.register(1)?;
+                match self.stack.pop()
+                {
+                    Some(top) => self.registers[a] = top,
+                    None => return self.fault("pop on an empty stack"),
+                }
+                self.program_counter = pc + 2;
+            },
+            // eq: set <a> to 1 if <b> is equal to <c>; set it to 0 otherwise
+            4 =>
+            {
+                let a = self.register(1)?;
+                self.registers[a] = if self.value(2)? == self.value(3)? { 1 } else { 0 };
+                self.program_counter = pc + 4;
+            },
+            // gt: set <a> to 1 if <b> is greater than <c>; set it to 0 otherwise
+            5 =>
+            {
+                let a = self.register(1)?;
+                self.registers[a] = if self.value(2)? > self.value(3)? { 1 } else { 0 };
+                self.program_counter = pc + 4;
+            },
+            // jmp: jump to <a>
+            6 => self.program_counter = self.value(1)?,
+            // jt: if <a> is nonzero, jump to <b>
+            7 =>
+            {
+                let b = self.value(2)?;
+                self.program_counter = if self.value(1)? != 0 { b } else { pc + 3 };
+            },
+            // jf: if <a> is zero, jump to <b>
+            8 =>
+            {
+                let b = self.value(2)?;
+                self.program_counter = if self.value(1)? == 0 { b } else { pc + 3 };
+            },
+            // add: assign into <a> the sum of <b> and <c> (modulo 32768)
+            9 =>
+            {
+                let a = self.register(1)?;
+                self.registers[a] = ((self.value(2)? as u32 + self.value(3)? as u32) % MODULO) as u16;
+                self.program_counter = pc + 4;
+            },
+            // mult: store into <a> the product of <b> and <c> (modulo 32768)
+            10 =>
+            {
+                let a = self.register(1)?;
+                self.registers[a] = ((self.value(2)? as u32 * self.value(3)? as u32) % MODULO) as u16;
+                self.program_counter = pc + 4;
+            },
+            // mod: store into <a> the remainder of <b> divided by <c>
+            11 =>
+            {
+                let a = self.register(1)?;
+                let c = self.value(3)?;
+                if c == 0
+                {
+                    return self.fault("modulo by zero");
+                }
+                self.registers[a] = self.value(2)? % c;
+                self.program_counter = pc + 4;
+            },
+            // and: stores into <a> the bitwise and of <b> and <c>
+            12 =>
+            {
+                let a = self.register(1)?;
+                self.registers[a] = self.value(2)? & self.value(3)?;
+                self.program_counter = pc + 4;
+            },
+            // or: stores into <a> the bitwise or of <b> and <c>
+            13 =>
+            {
+                let a = self.register(1)?;
+                self.registers[a] = self.value(2)? | self.value(3)?;
+                self.program_counter = pc + 4;
+            },
+            // not: stores 15-bit bitwise inverse of <b> in <a>
+            14 =>
+            {
+                let a = self.register(1)?;
+                self.registers[a] = !self.value(2)? & 0x7fff;
+                self.program_counter = pc + 3;
+            },
+            // rmem: read memory at address <b> and write it to <a>
+            15 =>
+            {
+                let a = self.register(1)?;
+                let b = self.address(self.value(2)?)?;
+                self.registers[a] = self.memory[b];
+                self.program_counter = pc + 3;
+            },
+            // wmem: write the value from <b> into memory at address <a>
+            16 =>
+            {
+                let a = self.address(self.value(1)?)?;
+                self.memory[a] = self.value(2)?;
+                self.last_write = Some(a as u16);
+                self.program_counter = pc + 3;
+            },
+            // call: write the address of the next instruction to the stack
+            // and jump to <a>
+            17 =>
+            {
+                let a = self.value(1)?;
+                self.stack.push(pc + 2);
+                self.program_counter = a;
+            },
+            // ret: remove the top element from the stack and jump to it;
+            // empty stack = halt
+            18 =>
+            {
+                match self.stack.pop()
+                {
+                    Some(top) => self.program_counter = self.address(top)? as u16,
+                    None => return Err(ReferenceStop::Halt),
+                }
+            },
+            // out: write the character represented by ascii code <a> to the
+            // terminal
+            19 =>
+            {
+                self.output.push((self.value(1)? as u8) as char);
+                self.program_counter = pc + 2;
+            },
+            // in: read a character from the terminal and write its ascii
+            // code to <a>
+            20 =>
+            {
+                let a = self.register(1)?;
+                match self.input.pop_front()
+                {
+                    Some(c) => self.registers[a] = c as u16,
+                    None => return Err(ReferenceStop::InputNeeded),
+                }
+                self.program_counter = pc + 2;
+            },
+            // noop: no operation
+            21 => self.program_counter = pc + 1,
+            _ => return self.fault("unknown op code"),
+        }
+        Ok(())
+    }
+}
+
+// Runs `vm` and `reference` one instruction at a time for up to `max_steps`
+// instructions, comparing the program counter, registers, stack, output and
+// memory after each one. Both must be at the same point when called.
+//
+// Comparing all the memory after every instruction is too slow for whole
+// game runs, so only the word written by the instruction is checked right
+// away and the whole memory every `MEMORY_CHECK_INTERVAL` instructions. When
+// that finds a difference the span since the last check is replayed from
+// copies with the whole memory compared after every instruction, so the
+// reported step is always the first one that diverged.
+pub fn lockstep(vm : &mut VM, reference : &mut ReferenceVm, max_steps : u64) -> Result<LockstepEnd, Divergence>
+{
+    let mut checkpoint = Checkpoint { vm : vm.fork(), reference : reference.clone(), step : 0 };
+    let mut step = 0;
+    while step < max_steps
+    {
+        let end = step_both(vm, reference, step, false)?;
+        step += 1;
+        if end != StepEnd::Running
+        {
+            return Ok(LockstepEnd::Stopped(end));
+        }
+
+        if step % MEMORY_CHECK_INTERVAL == 0 || step == max_steps
+        {
+            if compare_memory(vm, reference).is_some()
+            {
+                return Err(checkpoint.replay(step));
+            }
+            checkpoint = Checkpoint { vm : vm.fork(), reference : reference.clone(), step };
+        }
+    }
+    Ok(LockstepEnd::StepBudgetExhausted)
+}
+
+const MEMORY_CHECK_INTERVAL : u64 = 4096;
+
+struct Checkpoint
+{
+    vm : VM,
+    reference : ReferenceVm,
+    step : u64,
+}
+
+impl Checkpoint
+{
+    // Runs again up to `to` comparing the whole memory after each step.
+    fn replay(mut self, to : u64) -> Divergence
+    {
+        for step in self.step..to
+        {
+            if let Err(divergence) = step_both(&mut self.vm, &mut self.reference, step, true)
+            {
+                return divergence;
+            }
+        }
+        Divergence
+        {
+            step : to,
+            program_counter : self.vm.program_counter(),
+            difference : "memory differs but the replay did not".to_owned(),
+        }
+    }
+}
+
+fn step_both(vm : &mut VM, reference : &mut ReferenceVm, step : u64, full_memory : bool) -> Result<StepEnd, Divergence>
+{
+    let program_counter = vm.program_counter();
+    let diverged = |difference : String| Divergence { step, program_counter, difference };
+
+    if reference.program_counter != program_counter
+    {
+        return Err(diverged(format!("program counter: vm {}, reference {}", program_counter, reference.program_counter)));
+    }
+
+    let output_len = reference.output.len();
+    let vm_end =
+        match vm.run(&StopConditions::new().max_steps(1))
+        {
+            StopReason::StepBudgetExhausted => StepEnd::Running,
+            StopReason::Halted => StepEnd::Halted,
+            StopReason::InputNeeded => StepEnd::InputNeeded,
+            StopReason::Fault(e) =>
+            {
+                // The state after a fault is not specified, only check that
+                // both refuse the instruction.
+                return match reference.step()
+                {
+                    Err(ReferenceStop::Fault(_)) => Ok(StepEnd::Faulted),
+                    end => Err(diverged(format!("vm faulted with {:?}, reference {:?}", e, end))),
+                };
+            },
+            reason => return Err(diverged(format!("vm stopped with {:?}", reason))),
+        };
+    let reference_end =
+        match reference.step()
+        {
+            Ok(()) => StepEnd::Running,
+            Err(ReferenceStop::Halt) => StepEnd::Halted,
+            Err(ReferenceStop::InputNeeded) => StepEnd::InputNeeded,
+            Err(ReferenceStop::Fault(message)) =>
+                return Err(diverged(format!("vm {:?}, reference faulted: {}", vm_end, message))),
+        };
+    if vm_end != reference_end
+    {
+        return Err(diverged(format!("vm {:?}, reference {:?}", vm_end, reference_end)));
+    }
+
+    if vm.program_counter() != reference.program_counter
+    {
+        return Err
+        (
+            diverged(format!("next program counter: vm {}, reference {}", vm.program_counter(), reference.program_counter))
+        );
+    }
+    for r in 0..8
+    {
+        if vm.registers()[r] != reference.registers[r]
+        {
+            return Err(diverged(format!("register {}: vm {}, reference {}", r, vm.registers()[r], reference.registers[r])));
+        }
+    }
+    if vm.stack() != &reference.stack[..]
+    {
+        return Err(diverged(format!("stack: vm {:?}, reference {:?}", vm.stack(), reference.stack)));
+    }
+    let output = vm.take_output();
+    if output != reference.output[output_len..]
+    {
+        return Err(diverged(format!("output: vm {:?}, reference {:?}", output, &reference.output[output_len..])));
+    }
+    if let Some(address) = reference.last_write
+    {
+        let vm_word = vm.memory().get(address as usize);
+        let reference_word = reference.memory[address as usize];
+        if vm_word != Some(reference_word)
+        {
+            return Err(diverged(format!("memory {}: vm {:?}, reference {}", address, vm_word, reference_word)));
+        }
+    }
+    if full_memory
+    {
+        if let Some(difference) = compare_memory(vm, reference)
+        {
+            return Err(diverged(difference));
+        }
+    }
+    Ok(vm_end)
+}
+
+fn compare_memory(vm : &VM, reference : &ReferenceVm) -> Option<String>
+{
+    vm.memory().iter()
+        .zip(reference.memory.iter())
+        .position(|(vm_word, reference_word)| vm_word != reference_word)
+        .map(|address| format!("memory {}: vm {}, reference {}", address, vm.memory()[address], reference.memory[address]))
+}
+
+// A VM set up to match `ReferenceVm::new(program)`: strict conformance,
+// queued input and buffered output.
+pub fn vm_for(program : &[u16]) -> VM
+{
+    let mut vm = VM::new(program.to_vec());
+    vm.set_conformance(Conformance::strict());
+    vm.set_input(Input::Queue(VecDeque::new()));
+    vm.set_output(Output::Buffer(String::new()));
+    vm
+}
@@ -0,0 +1,77 @@
+use vm::{VM, RunFailure};
+
+pub type Predicate = Box<dyn Fn(&VM) -> bool>;
+
+// What makes `VM::run` stop besides halting or failing. Built with
+// chained calls, e.g. `StopConditions::new().max_steps(1000).stop_at(42)`.
+#[derive(Default)]
+pub struct StopConditions
+{
+    pub max_steps : Option<u64>,
+    pub breakpoints : Vec<u16>,
+    pub stop_on_input : bool,
+    pub output_pattern : Option<String>,
+    pub predicate : Option<Predicate>,
+}
+
+#[derive(Debug)]
+pub enum StopReason
+{
+    StepBudgetExhausted,
+    PcReached(u16),
+    InputNeeded,
+    Halted,
+    OutputMatched(String),
+    PredicateMatched,
+    Fault(RunFailure),
+}
+
+impl StopConditions
+{
+    pub fn new() -> StopConditions
+    {
+        StopConditions::default()
+    }
+
+    pub fn max_steps(mut self, max_steps : u64) -> StopConditions
+    {
+        self.max_steps = Some(max_steps);
+        self
+    }
+
+    pub fn stop_at(mut self, program_counter : u16) -> StopConditions
+    {
+        self.breakpoints.push(program_counter);
+        self
+    }
+
+    // Stop before reading a new input line instead of waiting for it.
+    pub fn stop_on_input(mut self) -> StopConditions
+    {
+        self.stop_on_input = true;
+        self
+    }
+
+    // Stop once the output printed during the run contains `pattern`.
+    pub fn output_contains(mut self, pattern : &str) -> StopConditions
+    {
+        self.output_pattern = Some(pattern.to_owned());
+        self
+    }
+
+    pub fn when<F>(mut self, predicate : F) -> StopConditions
+        where F : Fn(&VM) -> bool + 'static
+    {
+        self.predicate = Some(Box::new(predicate));
+        self
+    }
+
+    // True when the conditions can only trigger between basic blocks, so the
+    // block backend does not need to run one instruction at a time.
+    pub fn is_coarse(&self) -> bool
+    {
+        self.breakpoints.is_empty() &&
+            self.output_pattern.is_none() &&
+            self.predicate.is_none()
+    }
+}
@@ -0,0 +1,101 @@
+use std::ops::Deref;
+use state_hash;
+
+// The VM stack, keeping its part of the state hash up to date. Slots
+// pushed by `call` remember the call so that return addresses can be told
+// apart from data; the marks are not part of the state hash.
+#[derive(Clone, Default, PartialEq, Eq, Hash)]
+pub struct Stack
+{
+    words : Vec<u16>,
+    calls : Vec<Option<Call>>,
+    hash : u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Call
+{
+    // Address of the `call` instruction.
+    pub caller : u16,
+    // Where the call jumped to.
+    pub entry : u16,
+}
+
+// One function activation, `call` is None for the outermost frame.
+// `base` is the index of the first data slot, the return address sits
+// just below it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame
+{
+    pub call : Option<Call>,
+    pub base : usize,
+    pub data : Vec<u16>,
+}
+
+impl Stack
+{
+    pub fn new() -> Stack
+    {
+        Stack::default()
+    }
+
+    pub fn push(&mut self, value : u16)
+    {
+        self.push_slot(value, None);
+    }
+
+    // Pushes the return address of `call`.
+    pub fn push_return(&mut self, value : u16, call : Call)
+    {
+        self.push_slot(value, Some(call));
+    }
+
+    pub fn pop(&mut self) -> Option<u16>
+    {
+        let value = self.words.pop()?;
+        self.calls.pop();
+        self.hash = self.hash.wrapping_sub(state_hash::term(state_hash::STACK, self.words.len(), value));
+        Some(value)
+    }
+
+    // This stack's part of the VM state hash.
+    pub fn state_hash(&self) -> u64
+    {
+        self.hash
+    }
+
+    // Frames from the innermost to the outermost, split at the slots pushed
+    // by `call`.
+    pub fn backtrace(&self) -> Vec<Frame>
+    {
+        let mut frames = vec!();
+        let mut end = self.words.len();
+        for slot in (0..self.words.len()).rev()
+        {
+            if let Some(call) = self.calls[slot]
+            {
+                frames.push(Frame { call : Some(call), base : slot + 1, data : self.words[slot + 1..end].to_vec() });
+                end = slot;
+            }
+        }
+        frames.push(Frame { call : None, base : 0, data : self.words[..end].to_vec() });
+        frames
+    }
+
+    fn push_slot(&mut self, value : u16, call : Option<Call>)
+    {
+        self.hash = self.hash.wrapping_add(state_hash::term(state_hash::STACK, self.words.len(), value));
+        self.words.push(value);
+        self.calls.push(call);
+    }
+}
+
+impl Deref for Stack
+{
+    type Target = [u16];
+
+    fn deref(&self) -> &[u16]
+    {
+        &self.words
+    }
+}
@@ -0,0 +1,179 @@
+extern crate serde_json;
+
+use std::fmt::Write;
+use std::fs;
+use std::io;
+use self::serde_json::{Value, json};
+use image_diff;
+use image_diff::ChangedRange;
+use vm::VM;
+
+// A VM state as written by the `dump` command, one number per line in
+// registers.txt, stack.txt, program_counter.txt, step_number.txt and
+// memory.txt.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SavedState
+{
+    pub registers : Vec<u16>,
+    pub stack : Vec<u16>,
+    pub program_counter : u16,
+    pub step : u64,
+    pub memory : Vec<u16>,
+}
+
+#[derive(Debug)]
+pub enum LoadStateFailure
+{
+    CannotRead(String, io::Error),
+    InvalidNumber(String, usize),
+}
+
+impl SavedState
+{
+    pub fn from_vm(vm : &VM) -> SavedState
+    {
+        SavedState
+        {
+            registers : vm.registers().to_vec(),
+            stack : vm.stack().to_vec(),
+            program_counter : vm.program_counter(),
+            step : vm.step_nb(),
+            memory : vm.memory().to_vec(),
+        }
+    }
+
+    // Reads a dump directory.
+    pub fn load(dir : &str) -> Result<SavedState, LoadStateFailure>
+    {
+        let program_counter : Vec<u16> = read_numbers(dir, "program_counter.txt")?;
+        let step : Vec<u64> = read_numbers(dir, "step_number.txt")?;
+        Ok
+        (
+            SavedState
+            {
+                registers : read_numbers(dir, "registers.txt")?,
+                stack : read_numbers(dir, "stack.txt")?,
+                program_counter : program_counter.first().cloned().unwrap_or(0),
+                step : step.first().cloned().unwrap_or(0),
+                memory : read_numbers(dir, "memory.txt")?,
+            }
+        )
+    }
+}
+
+fn read_numbers<T : ::std::str::FromStr>(dir : &str, name : &str) -> Result<Vec<T>, LoadStateFailure>
+{
+    let file_name = format!("{}/{}", dir.trim_end_matches('/'), name);
+    let text = fs::read_to_string(&file_name).map_err(|e| LoadStateFailure::CannotRead(file_name.clone(), e))?;
+    text.lines()
+        .enumerate()
+        .filter(|&(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| line.trim().parse().map_err(|_| LoadStateFailure::InvalidNumber(file_name.clone(), i + 1)))
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RegisterChange
+{
+    pub register : usize,
+    pub before : u16,
+    pub after : u16,
+}
+
+// The stacks share their bottom `kept` slots, the slots above are popped
+// from the first state and pushed in the second.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StackChange
+{
+    pub kept : usize,
+    pub removed : Vec<u16>,
+    pub added : Vec<u16>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StateDiff
+{
+    pub program_counter : (u16, u16),
+    pub step : (u64, u64),
+    pub registers : Vec<RegisterChange>,
+    pub stack : StackChange,
+    pub memory : Vec<ChangedRange>,
+}
+
+pub fn diff(before : &SavedState, after : &SavedState) -> StateDiff
+{
+    let registers =
+        before.registers
+            .iter()
+            .zip(after.registers.iter())
+            .enumerate()
+            .filter(|&(_, (old, new))| old != new)
+            .map(|(register, (&before, &after))| RegisterChange { register, before, after })
+            .collect();
+    let kept = before.stack.iter().zip(after.stack.iter()).take_while(|&(old, new)| old == new).count();
+    StateDiff
+    {
+        program_counter : (before.program_counter, after.program_counter),
+        step : (before.step, after.step),
+        registers,
+        stack : StackChange { kept, removed : before.stack[kept..].to_vec(), added : after.stack[kept..].to_vec() },
+        memory : image_diff::diff(&before.memory, &after.memory),
+    }
+}
+
+impl StateDiff
+{
+    pub fn is_empty(&self) -> bool
+    {
+        self.program_counter.0 == self.program_counter.1 &&
+            self.step.0 == self.step.1 &&
+            self.registers.is_empty() &&
+            self.stack.removed.is_empty() &&
+            self.stack.added.is_empty() &&
+            self.memory.is_empty()
+    }
+
+    pub fn to_text(&self) -> String
+    {
+        let mut text = String::new();
+        let _ = writeln!(text, "pc {} -> {}", self.program_counter.0, self.program_counter.1);
+        let _ = writeln!(text, "step {} -> {} ({:+})", self.step.0, self.step.1, self.step.1 as i64 - self.step.0 as i64);
+        for change in &self.registers
+        {
+            let _ = writeln!(text, "r{} {} -> {}", change.register, change.before, change.after);
+        }
+        let _ =
+            writeln!
+            (
+                text,
+                "stack: {} slots kept, removed {:?}, added {:?}",
+                self.stack.kept,
+                self.stack.removed,
+                self.stack.added
+            );
+        text.push_str(&image_diff::describe(&self.memory));
+        text
+    }
+
+    pub fn to_json(&self) -> Value
+    {
+        let registers : Vec<Value> =
+            self.registers
+                .iter()
+                .map(|change| json!({ "register" : change.register, "before" : change.before, "after" : change.after }))
+                .collect();
+        let memory : Vec<Value> =
+            self.memory
+                .iter()
+                .map(|range| json!({ "start" : range.start, "end" : range.end(), "before" : range.before, "after" : range.after }))
+                .collect();
+        json!
+        ({
+            "program_counter" : { "before" : self.program_counter.0, "after" : self.program_counter.1 },
+            "step" : { "before" : self.step.0, "after" : self.step.1, "delta" : self.step.1 as i64 - self.step.0 as i64 },
+            "registers" : registers,
+            "stack" : { "kept" : self.stack.kept, "removed" : self.stack.removed, "added" : self.stack.added },
+            "memory" : memory,
+        })
+    }
+}
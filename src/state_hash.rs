@@ -0,0 +1,25 @@
+// The state hash is the wrapping sum of one term per memory word, register,
+// stack slot and the program counter. Each term only depends on where the
+// word is and what it holds, so a write updates the hash by swapping one
+// term for another instead of hashing everything again. The mixer is fixed
+// so hashes can be stored and compared across builds.
+
+pub const MEMORY : u64 = 1;
+pub const REGISTER : u64 = 2;
+pub const STACK : u64 = 3;
+pub const PROGRAM_COUNTER : u64 = 4;
+
+// splitmix64 finalizer over the word kind, its index and its value.
+pub fn term(kind : u64, index : usize, value : u16) -> u64
+{
+    let mut x = (kind << 56) ^ ((index as u64) << 16) ^ value as u64;
+    x = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^ (x >> 31)
+}
+
+pub fn update(hash : u64, kind : u64, index : usize, old_value : u16, new_value : u16) -> u64
+{
+    hash.wrapping_sub(term(kind, index, old_value)).wrapping_add(term(kind, index, new_value))
+}
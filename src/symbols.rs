@@ -0,0 +1,108 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io;
+
+// Labels from an assembler symbol file, one `label address` or
+// `address label` pair per line. Addresses are word addresses in decimal or
+// with a 0x prefix in hexadecimal, `#` starts a comment.
+#[derive(Debug, Clone, Default)]
+pub struct Symbols
+{
+    by_name : HashMap<String, u16>,
+    by_address : BTreeMap<u16, String>,
+}
+
+#[derive(Debug)]
+pub enum SymbolsFailure
+{
+    CannotRead(io::Error),
+    InvalidLine(usize, String),
+}
+
+impl Symbols
+{
+    pub fn new() -> Symbols
+    {
+        Symbols::default()
+    }
+
+    pub fn load(file_name : &str) -> Result<Symbols, SymbolsFailure>
+    {
+        let text = fs::read_to_string(file_name).map_err(SymbolsFailure::CannotRead)?;
+        Symbols::parse(&text)
+    }
+
+    pub fn parse(text : &str) -> Result<Symbols, SymbolsFailure>
+    {
+        let mut symbols = Symbols::new();
+        for (i, line) in text.lines().enumerate()
+        {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty()
+            {
+                continue;
+            }
+            let fields : Vec<&str> = line.split_whitespace().collect();
+            let symbol = match fields[..]
+            {
+                [first, second] =>
+                    match (parse_address(first), parse_address(second))
+                    {
+                        (Some(address), None) => Some((second, address)),
+                        (None, Some(address)) => Some((first, address)),
+                        _ => None,
+                    },
+                _ => None,
+            };
+            match symbol
+            {
+                Some((name, address)) => symbols.insert(name, address),
+                None => return Err(SymbolsFailure::InvalidLine(i + 1, line.to_owned())),
+            }
+        }
+        Ok(symbols)
+    }
+
+    pub fn insert(&mut self, name : &str, address : u16)
+    {
+        self.by_name.insert(name.to_owned(), address);
+        self.by_address.insert(address, name.to_owned());
+    }
+
+    pub fn address_of(&self, name : &str) -> Option<u16>
+    {
+        self.by_name.get(name).cloned()
+    }
+
+    pub fn name_of(&self, address : u16) -> Option<&str>
+    {
+        self.by_address.get(&address).map(|name| name.as_str())
+    }
+
+    // `label+offset` from the closest label at or before `address`, or the
+    // address alone when there is none.
+    pub fn describe(&self, address : u16) -> String
+    {
+        match self.by_address.range(..=address).next_back()
+        {
+            Some((&label_address, name)) if label_address == address => name.clone(),
+            Some((&label_address, name)) => format!("{}+{}", name, address - label_address),
+            None => format!("{}", address),
+        }
+    }
+
+    // A label or an address.
+    pub fn resolve(&self, text : &str) -> Option<u16>
+    {
+        self.address_of(text).or_else(|| parse_address(text))
+    }
+}
+
+pub fn parse_address(text : &str) -> Option<u16>
+{
+    match text.strip_prefix("0x")
+    {
+        Some(hex) => u16::from_str_radix(hex, 16).ok(),
+        None => text.parse().ok(),
+    }
+}
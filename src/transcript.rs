@@ -0,0 +1,270 @@
+extern crate chrono;
+
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::Write;
+use std::sync::Arc;
+use self::chrono::{NaiveDateTime, Timelike};
+
+const TIME_FORMAT : &str = "%Y-%m-%dT%H:%M:%S%.3f";
+
+// One line of a session. `step` is the step of the `in` that read the
+// line, or of the `out` that printed the first character of the line,
+// `time` is when that happened, to the millisecond.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Record
+{
+    Input { step : u64, time : NaiveDateTime, line : String },
+    Output { step : u64, time : NaiveDateTime, line : String },
+}
+
+impl Record
+{
+    pub fn step(&self) -> u64
+    {
+        match *self
+        {
+            Record::Input { step, .. } | Record::Output { step, .. } => step,
+        }
+    }
+
+    pub fn time(&self) -> NaiveDateTime
+    {
+        match *self
+        {
+            Record::Input { time, .. } | Record::Output { time, .. } => time,
+        }
+    }
+
+    // Same direction, step and line, whenever it happened.
+    pub fn same_event(&self, other : &Record) -> bool
+    {
+        match (self, other)
+        {
+            (&Record::Input { step : a, line : ref x, .. }, &Record::Input { step : b, line : ref y, .. }) |
+            (&Record::Output { step : a, line : ref x, .. }, &Record::Output { step : b, line : ref y, .. }) =>
+                a == b && x == y,
+            _ => false,
+        }
+    }
+}
+
+// Records the lines the program reads and prints. The file is written as
+// the session goes, one record per line:
+//
+//     # transcript started 2026-10-19T10:00:00
+//     > 0 2026-10-19T10:00:00.120 Welcome to the Synacor Challenge!
+//     < 893209 2026-10-19T10:00:04.250 go north
+//
+// `>` lines are output and `<` lines input, each with its step and time.
+// Output not ended by a new line yet is written when the next line is read
+// or by `finish`.
+#[derive(Debug, Clone)]
+pub struct Transcript
+{
+    file_name : Option<String>,
+    file : Option<Arc<File>>,
+    records : Vec<Record>,
+    current_line : String,
+    current_step : u64,
+    current_time : NaiveDateTime,
+}
+
+#[derive(Debug)]
+pub enum TranscriptFailure
+{
+    CannotRead(io::Error),
+    InvalidLine(usize, String),
+}
+
+impl Transcript
+{
+    // Starts `file_name` over.
+    pub fn new(file_name : &str) -> Transcript
+    {
+        let header = format!("# transcript started {}\n", chrono::Local::now().format("%Y-%m-%dT%H:%M:%S"));
+        let file =
+            File::create(file_name)
+                .and_then(|mut file| file.write_all(header.as_bytes()).map(|_| file));
+        let file = match file
+        {
+            Ok(file) => Some(Arc::new(file)),
+            Err(e) =>
+            {
+                eprintln!("cannot create transcript {}: {}", file_name, e);
+                None
+            },
+        };
+        Transcript { file_name : Some(file_name.to_owned()), file, ..Transcript::in_memory() }
+    }
+
+    pub fn in_memory() -> Transcript
+    {
+        Transcript
+        {
+            file_name : None,
+            file : None,
+            records : vec!(),
+            current_line : String::new(),
+            current_step : 0,
+            current_time : NaiveDateTime::default(),
+        }
+    }
+
+    pub fn records(&self) -> &[Record]
+    {
+        &self.records
+    }
+
+    // A line without its new line is the end of the input, not a line.
+    pub fn on_input_line(&mut self, line : &str, step : u64)
+    {
+        if line.is_empty()
+        {
+            return;
+        }
+        self.flush_output();
+        self.push(Record::Input { step, time : now(), line : line.trim_end_matches('\n').to_owned() });
+    }
+
+    pub fn on_output_char(&mut self, c : char, step : u64)
+    {
+        if self.current_line.is_empty()
+        {
+            self.current_step = step;
+            self.current_time = now();
+        }
+        if c == '\n'
+        {
+            let line = self.current_line.split_off(0);
+            self.push(Record::Output { step : self.current_step, time : self.current_time, line });
+        }
+        else
+        {
+            self.current_line.push(c);
+        }
+    }
+
+    // Writes the output printed since the last new line.
+    pub fn finish(&mut self)
+    {
+        self.flush_output();
+    }
+
+    fn flush_output(&mut self)
+    {
+        if !self.current_line.is_empty()
+        {
+            let line = self.current_line.split_off(0);
+            self.push(Record::Output { step : self.current_step, time : self.current_time, line });
+        }
+    }
+
+    fn push(&mut self, record : Record)
+    {
+        if let (Some(file), Some(file_name)) = (self.file.as_ref(), self.file_name.as_ref())
+        {
+            // Clones share the handle, writes through `&File` need no lock.
+            let mut file : &File = file;
+            if let Err(e) = writeln!(file, "{}", format_record(&record))
+            {
+                eprintln!("cannot write transcript {}: {}", file_name, e);
+            }
+        }
+        self.records.push(record);
+    }
+}
+
+// The current local time, cut to the millisecond the file keeps.
+fn now() -> NaiveDateTime
+{
+    let time = chrono::Local::now().naive_local();
+    time.with_nanosecond(time.nanosecond() / 1_000_000 * 1_000_000).unwrap_or(time)
+}
+
+fn format_record(record : &Record) -> String
+{
+    match *record
+    {
+        Record::Input { step, time, ref line } => format!("< {} {} {}", step, time.format(TIME_FORMAT), line),
+        Record::Output { step, time, ref line } => format!("> {} {} {}", step, time.format(TIME_FORMAT), line),
+    }
+}
+
+pub fn load(file_name : &str) -> Result<Vec<Record>, TranscriptFailure>
+{
+    let text = fs::read_to_string(file_name).map_err(TranscriptFailure::CannotRead)?;
+    parse(&text)
+}
+
+// Reads the records back, skipping comments.
+pub fn parse(text : &str) -> Result<Vec<Record>, TranscriptFailure>
+{
+    let mut records = vec!();
+    for (i, line) in text.lines().enumerate()
+    {
+        if line.is_empty() || line.starts_with('#')
+        {
+            continue;
+        }
+        let invalid_line = || TranscriptFailure::InvalidLine(i + 1, line.to_owned());
+        let mut fields = line.splitn(4, ' ');
+        let kind = fields.next().ok_or_else(invalid_line)?;
+        let step = fields.next().and_then(|step| step.parse().ok()).ok_or_else(invalid_line)?;
+        let time =
+            fields.next()
+                .and_then(|time| NaiveDateTime::parse_from_str(time, TIME_FORMAT).ok())
+                .ok_or_else(invalid_line)?;
+        let text = fields.next().unwrap_or("").to_owned();
+        match kind
+        {
+            "<" => records.push(Record::Input { step, time, line : text }),
+            ">" => records.push(Record::Output { step, time, line : text }),
+            _ => return Err(invalid_line()),
+        }
+    }
+    Ok(records)
+}
+
+// The input lines, to replay the session as an input script.
+pub fn input_script(records : &[Record]) -> Vec<String>
+{
+    records
+        .iter()
+        .filter_map
+        (
+            |record|
+                match *record
+                {
+                    Record::Input { ref line, .. } => Some(line.clone()),
+                    Record::Output { .. } => None,
+                }
+        )
+        .collect()
+}
+
+// Index of the first record that differs between two runs, steps
+// included but not times, with both records. None when the transcripts
+// are the same.
+pub fn first_difference(a : &[Record], b : &[Record]) -> Option<(usize, Option<Record>, Option<Record>)>
+{
+    let same = |x : Option<&Record>, y : Option<&Record>| match (x, y)
+    {
+        (Some(x), Some(y)) => x.same_event(y),
+        (x, y) => x.is_none() && y.is_none(),
+    };
+    (0..a.len().max(b.len()))
+        .find(|&i| !same(a.get(i), b.get(i)))
+        .map(|i| (i, a.get(i).cloned(), b.get(i).cloned()))
+}
+
+pub fn describe_difference(a : &[Record], b : &[Record]) -> String
+{
+    let describe = |record : Option<Record>| record.map_or("end of transcript".to_owned(), |record| format_record(&record));
+    match first_difference(a, b)
+    {
+        None => format!("same {} records\n", a.len()),
+        Some((i, first, second)) => format!("records differ at {}\n- {}\n+ {}\n", i, describe(first), describe(second)),
+    }
+}
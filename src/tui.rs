@@ -0,0 +1,413 @@
+extern crate ratatui;
+
+use std::collections::VecDeque;
+use std::io;
+use std::time::Duration;
+use self::ratatui::{Frame, Terminal};
+use self::ratatui::backend::Backend;
+use self::ratatui::crossterm::event;
+use self::ratatui::crossterm::event::{Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use self::ratatui::layout::{Constraint, Layout, Rect};
+use self::ratatui::style::{Modifier, Style};
+use self::ratatui::text::{Line, Span};
+use self::ratatui::widgets::{Block, Paragraph};
+use console::{Input, Output};
+use disassembler;
+use memory_view::{MemoryView, WordFormat};
+use run_until::{StopConditions, StopReason};
+use vm::VM;
+
+// Instructions run between two redraws while the program is running.
+const STEPS_PER_TICK : u64 = 20_000;
+const MEMORY_PAGE : i32 = 64;
+// Disassembly lines shown before the program counter when they can be found.
+const LINES_BEFORE_PC : usize = 4;
+const KEYS : &str = " F5 pause/resume  F10 step  F2 snapshot  F3 restore  PgUp/PgDn memory  mem <command>  Esc quit ";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum State
+{
+    Running,
+    Paused,
+    WaitingForInput,
+    Stopped,
+}
+
+// Terminal front-end: the game output and the input line on the left,
+// registers, call stack, disassembly around the program counter and a
+// memory hex view on the right.
+pub struct Tui
+{
+    vm : VM,
+    state : State,
+    output : String,
+    input : String,
+    status : String,
+    snapshot : Option<(VM, String)>,
+    memory_view : MemoryView,
+    quit : bool,
+    // Set after an input line and on faults, which can print diagnostics
+    // on stderr behind the screen's back; the next draw repaints it all.
+    needs_clear : bool,
+}
+
+impl Tui
+{
+    pub fn new(mut vm : VM) -> Tui
+    {
+        vm.set_input(Input::Queue(VecDeque::new()));
+        vm.set_output(Output::Buffer(String::new()));
+        Tui
+        {
+            vm,
+            state : State::Running,
+            output : String::new(),
+            input : String::new(),
+            status : String::new(),
+            snapshot : None,
+            memory_view : MemoryView::new(),
+            quit : false,
+            needs_clear : false,
+        }
+    }
+
+    pub fn vm(&self) -> &VM
+    {
+        &self.vm
+    }
+
+    pub fn state(&self) -> State
+    {
+        self.state
+    }
+
+    pub fn output(&self) -> &str
+    {
+        &self.output
+    }
+
+    // Takes over the terminal until the user quits.
+    pub fn run(&mut self) -> io::Result<()>
+    {
+        let mut terminal = ratatui::init();
+        let result = self.event_loop(&mut terminal);
+        ratatui::restore();
+        result
+    }
+
+    fn event_loop<B : Backend>(&mut self, terminal : &mut Terminal<B>) -> io::Result<()>
+    {
+        while !self.quit
+        {
+            if self.needs_clear
+            {
+                terminal.clear()?;
+                self.needs_clear = false;
+            }
+            terminal.draw(|frame| self.draw(frame))?;
+
+            let timeout = if self.state == State::Running { Duration::ZERO } else { Duration::from_millis(100) };
+            if event::poll(timeout)?
+            {
+                if let Event::Key(key) = event::read()?
+                {
+                    if key.kind == KeyEventKind::Press
+                    {
+                        self.handle_key(key);
+                    }
+                }
+            }
+            else if self.state == State::Running
+            {
+                self.tick();
+            }
+        }
+        Ok(())
+    }
+
+    // Runs the program for a while if it is running.
+    pub fn tick(&mut self)
+    {
+        if self.state == State::Running
+        {
+            let stop = self.vm.run(&StopConditions::new().max_steps(STEPS_PER_TICK).stop_on_input());
+            self.on_stop(stop);
+        }
+    }
+
+    pub fn handle_key(&mut self, key : KeyEvent)
+    {
+        match key.code
+        {
+            KeyCode::Esc => self.quit = true,
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => self.quit = true,
+            KeyCode::F(5) => self.toggle_pause(),
+            KeyCode::F(10) => self.step(),
+            KeyCode::F(2) => self.take_snapshot(),
+            KeyCode::F(3) => self.restore_snapshot(),
+            KeyCode::PageUp => self.memory_view.scroll(self.vm.memory(), -MEMORY_PAGE),
+            KeyCode::PageDown => self.memory_view.scroll(self.vm.memory(), MEMORY_PAGE),
+            KeyCode::Enter => self.submit_line(),
+            KeyCode::Backspace => { self.input.pop(); },
+            KeyCode::Char(c) => self.input.push(c),
+            _ => (),
+        }
+    }
+
+    fn toggle_pause(&mut self)
+    {
+        self.state =
+            match self.state
+            {
+                State::Paused => State::Running,
+                State::Running | State::WaitingForInput => State::Paused,
+                State::Stopped => State::Stopped,
+            };
+    }
+
+    fn step(&mut self)
+    {
+        if self.state == State::Stopped
+        {
+            return;
+        }
+        self.state = State::Paused;
+        let stop = self.vm.run(&StopConditions::new().max_steps(1));
+        self.on_stop(stop);
+    }
+
+    // `mem` commands drive the memory pane, other lines go to the program.
+    fn submit_line(&mut self)
+    {
+        let line = self.input.split_off(0);
+        self.output.push_str(&line);
+        self.output.push('\n');
+        if line == "mem" || line.starts_with("mem ")
+        {
+            match self.memory_view.execute(&mut self.vm, &line[3..])
+            {
+                Ok(text) => self.output.push_str(&text),
+                Err(e) => self.output.push_str(&format!("{:?}\n", e)),
+            }
+            return;
+        }
+        self.vm.push_input_line(&line);
+        self.needs_clear = true;
+        if self.state == State::WaitingForInput
+        {
+            self.state = State::Running;
+        }
+    }
+
+    fn take_snapshot(&mut self)
+    {
+        self.snapshot = Some((self.vm.fork(), self.output.clone()));
+        self.status = format!("snapshot at step {}", self.vm.step_nb());
+    }
+
+    fn restore_snapshot(&mut self)
+    {
+        match self.snapshot
+        {
+            None => self.status = "no snapshot".to_owned(),
+            Some((ref vm, ref output)) =>
+            {
+                let mut vm = vm.fork();
+                self.vm.hand_over_recorders(&mut vm);
+                self.vm = vm;
+                self.output = output.clone();
+                self.state = State::Paused;
+                self.status = format!("restored step {}", self.vm.step_nb());
+            },
+        }
+    }
+
+    fn on_stop(&mut self, stop : StopReason)
+    {
+        self.output.push_str(&self.vm.take_output());
+        match stop
+        {
+            StopReason::InputNeeded => self.state = State::WaitingForInput,
+            StopReason::Halted =>
+            {
+                self.state = State::Stopped;
+                self.status = "halted".to_owned();
+            },
+            StopReason::Fault(failure) =>
+            {
+                self.state = State::Stopped;
+                self.status = format!("{:?}", failure);
+                self.needs_clear = true;
+            },
+            _ => (),
+        }
+    }
+
+    pub fn draw(&self, frame : &mut Frame)
+    {
+        let [left, right] = Layout::horizontal([Constraint::Percentage(60), Constraint::Percentage(40)]).areas(frame.area());
+        let [output_area, input_area] = Layout::vertical([Constraint::Min(3), Constraint::Length(3)]).areas(left);
+        let [registers_area, stack_area, code_area, memory_area] =
+            Layout::vertical
+            ([
+                Constraint::Length(5),
+                Constraint::Length(8),
+                Constraint::Min(6),
+                Constraint::Length(10),
+            ])
+            .areas(right);
+
+        self.draw_output(frame, output_area);
+        self.draw_input(frame, input_area);
+        self.draw_registers(frame, registers_area);
+        self.draw_stack(frame, stack_area);
+        self.draw_code(frame, code_area);
+        self.draw_memory(frame, memory_area);
+    }
+
+    // The tail of the output, wrapped by hand so the last line stays in view.
+    fn draw_output(&self, frame : &mut Frame, area : Rect)
+    {
+        let width = area.width.saturating_sub(2).max(1) as usize;
+        let height = area.height.saturating_sub(2) as usize;
+        let mut lines : Vec<String> = vec!();
+        for line in self.output.split('\n')
+        {
+            let chars : Vec<char> = line.chars().collect();
+            if chars.is_empty()
+            {
+                lines.push(String::new());
+            }
+            for chunk in chars.chunks(width)
+            {
+                lines.push(chunk.iter().collect());
+            }
+        }
+        let start = lines.len().saturating_sub(height);
+        let text : Vec<Line> = lines[start..].iter().map(|line| Line::raw(line.clone())).collect();
+        frame.render_widget(Paragraph::new(text).block(Block::bordered().title(" Output ").title_bottom(KEYS)), area);
+    }
+
+    fn draw_input(&self, frame : &mut Frame, area : Rect)
+    {
+        let state =
+            match self.state
+            {
+                State::Running => "running",
+                State::Paused => "paused",
+                State::WaitingForInput => "waiting for input",
+                State::Stopped => "stopped",
+            };
+        let title =
+            if self.status.is_empty() { format!(" Input - {} ", state) } else { format!(" Input - {} - {} ", state, self.status) };
+        let line = Line::from(vec!(Span::raw("> "), Span::raw(self.input.clone()), Span::styled(" ", Style::new().add_modifier(Modifier::REVERSED))));
+        frame.render_widget(Paragraph::new(line).block(Block::bordered().title(title)), area);
+    }
+
+    fn draw_registers(&self, frame : &mut Frame, area : Rect)
+    {
+        let registers = self.vm.registers();
+        let mut text : Vec<Line> =
+            registers
+                .chunks(4)
+                .enumerate()
+                .map
+                (
+                    |(row, values)|
+                    {
+                        let cells : Vec<String> =
+                            values.iter().enumerate().map(|(i, value)| format!("r{} {:5}", row * 4 + i, value)).collect();
+                        Line::raw(cells.join("  "))
+                    }
+                )
+                .collect();
+        text.push(Line::raw(format!("pc {:5}  step {}", self.vm.program_counter(), self.vm.step_nb())));
+        frame.render_widget(Paragraph::new(text).block(Block::bordered().title(" Registers ")), area);
+    }
+
+    // Innermost frame first.
+    fn draw_stack(&self, frame : &mut Frame, area : Rect)
+    {
+        let text : Vec<Line> =
+            self.vm
+                .backtrace()
+                .iter()
+                .map
+                (
+                    |stack_frame|
+                    {
+                        let data : Vec<String> = stack_frame.data.iter().map(|value| value.to_string()).collect();
+                        match stack_frame.call
+                        {
+                            Some(call) => Line::raw(format!("{} from {}: [{}]", call.entry, call.caller, data.join(", "))),
+                            None => Line::raw(format!("top: [{}]", data.join(", "))),
+                        }
+                    }
+                )
+                .collect();
+        frame.render_widget(Paragraph::new(text).block(Block::bordered().title(" Stack ")), area);
+    }
+
+    fn draw_code(&self, frame : &mut Frame, area : Rect)
+    {
+        let program_counter = self.vm.program_counter();
+        let height = area.height.saturating_sub(2) as usize;
+        let start = listing_start(self.vm.memory(), program_counter);
+        let text : Vec<Line> =
+            disassembler::listing(self.vm.memory(), start, height)
+                .iter()
+                .map
+                (
+                    |line|
+                    {
+                        let text = format!("{:5}  {}", line.address, line.text);
+                        if line.address == program_counter
+                        {
+                            Line::styled(format!("> {}", text), Style::new().add_modifier(Modifier::BOLD))
+                        }
+                        else
+                        {
+                            Line::raw(format!("  {}", text))
+                        }
+                    }
+                )
+                .collect();
+        frame.render_widget(Paragraph::new(text).block(Block::bordered().title(" Disassembly ")), area);
+    }
+
+    fn draw_memory(&self, frame : &mut Frame, area : Rect)
+    {
+        let rows = area.height.saturating_sub(2) as usize;
+        let text = self.memory_view.render(self.vm.memory(), rows);
+        let format =
+            match self.memory_view.format
+            {
+                WordFormat::Decimal => "dec",
+                WordFormat::Hex => "hex",
+                WordFormat::Ascii => "ascii",
+            };
+        let lines : Vec<Line> = text.lines().map(|line| Line::raw(line.to_owned())).collect();
+        frame.render_widget(Paragraph::new(lines).block(Block::bordered().title(format!(" Memory ({}) ", format))), area);
+    }
+}
+
+// Instructions have different sizes, so the listing before the program
+// counter is found by trying start addresses until one lines up with it.
+// Falls back to the program counter itself.
+fn listing_start(memory : &::memory::Memory, program_counter : u16) -> u16
+{
+    for back in (1..=LINES_BEFORE_PC as u16 * 4).rev()
+    {
+        let start = match program_counter.checked_sub(back)
+        {
+            Some(start) => start,
+            None => continue,
+        };
+        let lines = disassembler::listing(memory, start, LINES_BEFORE_PC + 1);
+        if let Some(i) = lines.iter().position(|line| line.address == program_counter)
+        {
+            return lines[i.saturating_sub(LINES_BEFORE_PC)].address;
+        }
+    }
+    program_counter
+}
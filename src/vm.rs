@@ -1,21 +1,57 @@
 extern crate chrono;
 use opcode;
 use opcode::*;
+use memory::Memory;
+use stack::{Stack, Call, Frame};
+use state_hash;
+use conformance::{Conformance, EmptyReturn, RegisterRead, MemoryRange, JumpTargets};
+use decode_cache::DecodeCache;
+use jit;
+use jit::{Backend, BlockCache};
+use run_until::{StopConditions, StopReason};
+use harvester::CodeHarvester;
+use code_watch::CodeWatch;
+use transcript::Transcript;
+use image_diff;
+use loader;
+use console::{Input, Output};
+use explorer::{Explorer, WorldGraph};
+use planner;
+use game_state::{GameLayout, Item, RoomInfo};
+use memory_view::MemoryView;
 use std::result::Result;
-use std::io;
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::mem;
 use std::fs;
 use std::io::Write;                                                                                                                                                             
 use std::fs::File; 
 
+const ADDRESS_SPACE_SIZE : usize = 32768;
+
+#[derive(Clone)]
 pub struct VM
 {
-    memory : Vec<u16>,
-    register : [u16; 8],
-    stack : Vec<u16>,
-    program_counter : u16,
+    memory : Memory,
+    pub(crate) register : [u16; 8],
+    pub(crate) stack : Stack,
+    pub(crate) program_counter : u16,
     pending_char : Vec<u8>,
     step_nb : u64,
-    print_debug : bool
+    print_debug : bool,
+    code_harvester : Option<CodeHarvester>,
+    code_watch : Option<CodeWatch>,
+    transcript : Option<Transcript>,
+    input : Input,
+    output : Output,
+    decode_cache : DecodeCache,
+    use_decode_cache : bool,
+    block_cache : BlockCache,
+    backend : Backend,
+    output_count : u64,
+    last_output : char,
+    conformance : Conformance,
+    memory_view : MemoryView,
 }
 
 #[derive(Debug)]
@@ -23,106 +59,331 @@ pub enum RunFailure
 {
     Halt,
     OpCodeParseFailure(opcode::ReadOpCodeFailure),
-    InvalidValue,
+    // An operand that is neither a number nor a register, or a register
+    // operand where the instruction needs a register.
+    InvalidValue { program_counter : u16, op_code : OpCode, value : u16 },
     InvalidInput,
     CannotPopStackIsEmpty,
     CannotReturnStackIsEmpty,
+    InputNeeded,
+    // A value read from the stack or memory that is not a 15 bit number.
+    InvalidLiteral { program_counter : u16, op_code : OpCode, value : u16 },
+    // A memory access outside of the loaded image.
+    InvalidAddress { program_counter : u16, op_code : OpCode, address : u16 },
+    DivisionByZero { program_counter : u16, op_code : OpCode },
+    // An instruction that would move past the end of the address space.
+    EndOfMemory { program_counter : u16, op_code : OpCode },
+    // A register read that keeps following registers that refer to each
+    // other under `RegisterRead::Chained`.
+    RegisterLoop { program_counter : u16, op_code : OpCode, register : usize },
+    // A register set from outside of the program to a value it cannot hold.
+    InvalidRegisterValue { register : usize, value : u16 },
 }
 
 impl VM
 {
-    fn print_debug(&self)
+    fn debug_text(&self) -> String
     {
-        println!("step {}", self.step_nb);
+        let mut text = format!("step {}\n", self.step_nb);
 
-        println!
+        text.push_str
         (
-            "current program counter {} in bytes 0x{}",
-            self.program_counter, 
-            format!("{:X}", self.program_counter * 2)
+            &format!
+            (
+                "current program counter {} in bytes 0x{:X}\n",
+                self.program_counter, 
+                self.program_counter * 2
+            )
         );
 
-        println!
-        (   "[{}, {}, {}, {}, {}, {}, {}, {}]", 
-            self.register[0],
-            self.register[1],
-            self.register[2],
-            self.register[3],
-            self.register[4],
-            self.register[5],
-            self.register[6],
-            self.register[7],
+        text.push_str
+        (
+            &format!
+            (   "[{}, {}, {}, {}, {}, {}, {}, {}]\n", 
+                self.register[0],
+                self.register[1],
+                self.register[2],
+                self.register[3],
+                self.register[4],
+                self.register[5],
+                self.register[6],
+                self.register[7],
+            )
         );
 
-        print!("[");
-        let mut is_first = true;
-        for val in &self.stack
+        // Outermost frame first, each call followed by its data slots.
+        for frame in self.stack.backtrace().iter().rev()
         {
-            if !is_first
+            let data : Vec<String> = frame.data.iter().map(|value| value.to_string()).collect();
+            match frame.call
             {
-                print!(", {}", val);
-            }
-            else
-            {
-                print!("{}", val);
-                is_first = false;
+                Some(call) => text.push_str(&format!("call {} from {}: [{}]\n", call.entry, call.caller, data.join(", "))),
+                None => text.push_str(&format!("[{}]\n", data.join(", "))),
             }
         }
-        println!("]");
+        text
+    }
+
+    // Text from the VM itself, e.g. tool commands, goes with the program
+    // output so that front ends capturing the output show it as well.
+    fn print(&mut self, text : &str)
+    {
+        self.output.write_str(text);
     }
 
     pub fn new (memory_ : Vec<u16>) -> VM
     {
         VM 
         {
-            memory : memory_,
+            memory : Memory::new(memory_),
             register : [0; 8], 
-            stack : vec!(),
+            stack : Stack::new(),
             program_counter : 0,
             pending_char : vec!(),
             step_nb : 0,
             print_debug : false,
+            code_harvester : None,
+            code_watch : None,
+            transcript : None,
+            input : Input::Stdin,
+            output : Output::Stdout,
+            decode_cache : DecodeCache::new(),
+            use_decode_cache : true,
+            block_cache : BlockCache::new(),
+            backend : Backend::Interpreter,
+            output_count : 0,
+            last_output : '\0',
+            conformance : Conformance::current(),
+            memory_view : MemoryView::new(),
+        }
+    }
+
+    pub fn set_backend(&mut self, backend : Backend)
+    {
+        self.backend = backend;
+        self.block_cache.clear();
+    }
+
+    // Switching to `MemoryRange::Full` grows the memory to the whole address
+    // space, switching back keeps the grown memory.
+    pub fn set_conformance(&mut self, conformance : Conformance)
+    {
+        self.conformance = conformance;
+        if conformance.memory_range == MemoryRange::Full
+        {
+            self.memory.grow(ADDRESS_SPACE_SIZE);
+        }
+    }
+
+    pub fn conformance(&self) -> Conformance
+    {
+        self.conformance
+    }
+
+    pub fn set_decode_cache_enabled(&mut self, enabled : bool)
+    {
+        self.use_decode_cache = enabled;
+        self.decode_cache.clear();
+    }
+
+    pub fn set_input(&mut self, input : Input)
+    {
+        self.input = input;
+    }
+
+    pub fn set_output(&mut self, output : Output)
+    {
+        self.output = output;
+    }
+
+    pub fn push_input_line(&mut self, line : &str)
+    {
+        self.input.push_line(line);
+    }
+
+    // Copy of this VM that can be run on its own from the current state.
+    // Memory pages are shared until one side writes to them. Queued input
+    // and buffered output are copied, a fork of a VM talking to the
+    // terminal gets an empty queue and buffer instead. The code harvester,
+    // code watch and transcript stay with this VM, so that speculative runs
+    // do not write to their files.
+    pub fn fork(&self) -> VM
+    {
+        let mut vm = self.clone();
+        if let Input::Stdin = vm.input
+        {
+            vm.input = Input::Queue(VecDeque::new());
+        }
+        if let Output::Stdout = vm.output
+        {
+            vm.output = Output::Buffer(String::new());
         }
+        vm.code_harvester = None;
+        vm.code_watch = None;
+        vm.transcript = None;
+        vm
+    }
+
+    // Moves the code harvester, code watch and transcript to `vm`, e.g. when
+    // a front end replaces its VM with a fork restored from a snapshot.
+    pub fn hand_over_recorders(&mut self, vm : &mut VM)
+    {
+        vm.code_harvester = self.code_harvester.take();
+        vm.transcript = self.transcript.take();
+        if let Some(code_watch) = self.code_watch.take()
+        {
+            vm.set_code_watch(code_watch);
+        }
+    }
+
+    pub fn take_output(&mut self) -> String
+    {
+        self.output.take()
     }
 
-    fn get_literal_value_or_register_value(&self, number : u16) -> 
+    pub fn memory(&self) -> &Memory
+    {
+        &self.memory
+    }
+
+    pub fn registers(&self) -> &[u16; 8]
+    {
+        &self.register
+    }
+
+    // Hash of the memory, registers, stack and program counter. Memory and
+    // stack keep their part up to date as they change, so this is O(1).
+    pub fn state_hash(&self) -> u64
+    {
+        let registers =
+            self.register.iter()
+                .enumerate()
+                .fold(0u64, |hash, (r, &value)| hash.wrapping_add(state_hash::term(state_hash::REGISTER, r, value)));
+        self.memory.state_hash()
+            .wrapping_add(self.stack.state_hash())
+            .wrapping_add(registers)
+            .wrapping_add(state_hash::term(state_hash::PROGRAM_COUNTER, 0, self.program_counter))
+    }
+
+    pub fn stack(&self) -> &[u16]
+    {
+        &self.stack
+    }
+
+    // Call frames from the innermost to the outermost.
+    pub fn backtrace(&self) -> Vec<Frame>
+    {
+        self.stack.backtrace()
+    }
+
+    pub fn program_counter(&self) -> u16
+    {
+        self.program_counter
+    }
+
+    pub fn step_nb(&self) -> u64
+    {
+        self.step_nb
+    }
+
+    pub fn set_code_harvester(&mut self, code_harvester : CodeHarvester)
+    {
+        self.code_harvester = Some(code_harvester);
+    }
+
+    pub fn code_harvester(&self) -> Option<&CodeHarvester>
+    {
+        self.code_harvester.as_ref()
+    }
+
+    // Translated blocks are dropped so that the watch sees them decoded.
+    pub fn set_code_watch(&mut self, code_watch : CodeWatch)
+    {
+        self.code_watch = Some(code_watch);
+        self.block_cache.clear();
+    }
+
+    pub fn code_watch(&self) -> Option<&CodeWatch>
+    {
+        self.code_watch.as_ref()
+    }
+
+    pub fn set_transcript(&mut self, transcript : Transcript)
+    {
+        self.transcript = Some(transcript);
+    }
+
+    pub fn transcript(&self) -> Option<&Transcript>
+    {
+        self.transcript.as_ref()
+    }
+
+    pub fn take_transcript(&mut self) -> Option<Transcript>
+    {
+        self.transcript.take()
+    }
+
+    pub(crate) fn get_literal_value_or_register_value(&self, op_code : OpCode, number : u16) -> 
         Result<u16, RunFailure>
     {
         let actual_value = check_number(number);
         match actual_value 
         {
-            ParsedNumber::InvalidNumber => Err(RunFailure::InvalidValue),
+            ParsedNumber::InvalidNumber => Err(self.invalid_value(op_code, number)),
             ParsedNumber::LiteralValue(val) =>
             {
                 Ok(val)
             },
-            ParsedNumber::Register(r) =>
+            ParsedNumber::Register(r) => self.read_register(op_code, r as usize),
+        }
+    }
+
+    // A chain longer than the number of registers goes round in a loop.
+    pub(crate) fn read_register(&self, op_code : OpCode, r : usize) -> Result<u16, RunFailure>
+    {
+        let mut current = r;
+        for _ in 0..self.register.len()
+        {
+            let val = self.register[current];
+            match (self.conformance.register_read, check_number(val))
             {
-                let val = self.register[r as usize];
-                self.get_literal_value_or_register_value(val)
+                (RegisterRead::Chained, ParsedNumber::Register(next)) => current = next as usize,
+                (RegisterRead::Chained, ParsedNumber::InvalidNumber) => return Err(self.invalid_value(op_code, val)),
+                _ => return Ok(val),
             }
         }
+        Err(RunFailure::RegisterLoop { program_counter : self.program_counter, op_code, register : r })
+    }
+
+    // The address of the instruction after the current one, there is none
+    // for an instruction that ends at the top of the address space.
+    fn next_address(&self, op_code : OpCode) -> Result<u16, RunFailure>
+    {
+        self.program_counter
+            .checked_add(op_code.size())
+            .ok_or(RunFailure::EndOfMemory { program_counter : self.program_counter, op_code })
+    }
+
+    fn invalid_value(&self, op_code : OpCode, value : u16) -> RunFailure
+    {
+        RunFailure::InvalidValue { program_counter : self.program_counter, op_code, value }
     }
 
     pub fn step(&mut self) -> 
         Result<(), RunFailure>
     {
-        let op_code_result = 
-            opcode::read_memory_to_op_code
-            (
-                &self.memory,
-                self.program_counter
-            );
+        let program_counter = self.program_counter;
+        let op_code_result = self.decode(program_counter);
 
+        // Diagnostics go to stderr, stdout may be a debugger connection.
         if self.print_debug
         {
-            self.print_debug();
+            eprint!("{}", self.debug_text());
         }
 
-        match op_code_result
+        if op_code_result.is_err()
         {
-            Err(_) => println!("current op code {:?}", op_code_result),
-            Ok(_) => (),
+            eprintln!("current op code {:?}", op_code_result);
         }
 
         match op_code_result
@@ -133,20 +394,188 @@ impl VM
             },
             Ok(op_code) => 
             {
+                if let Some(ref mut code_watch) = self.code_watch
+                {
+                    code_watch.on_decode(program_counter, op_code.size());
+                }
                 let result = self.handle_op_code(op_code);
-                self.step_nb = self.step_nb + 1;
+                self.count_step(&result);
                 result
             },
         }
     }
 
+    // Waiting for input does not execute the instruction, so it is not
+    // counted as a step.
+    fn count_step(&mut self, result : &Result<(), RunFailure>)
+    {
+        match *result
+        {
+            Err(RunFailure::InputNeeded) => (),
+            _ => self.step_nb += 1,
+        }
+    }
+
+    // Runs up to `max_steps` instructions with the selected backend,
+    // stopping at the first failure.
+    pub fn run_steps(&mut self, max_steps : u64) -> Result<(), RunFailure>
+    {
+        let last_step = self.step_nb.saturating_add(max_steps);
+        while self.step_nb < last_step
+        {
+            match self.backend
+            {
+                Backend::Interpreter => self.step()?,
+                Backend::BlockJit =>
+                {
+                    let remaining = last_step - self.step_nb;
+                    self.run_block(remaining)?
+                },
+            }
+        }
+        Ok(())
+    }
+
+    // True when the next instruction reads a new line and none is queued.
+    pub fn needs_input(&self) -> bool
+    {
+        self.pending_char.is_empty() &&
+            !self.input.has_queued_line() &&
+            self.memory.get(self.program_counter as usize) == Some(20)
+    }
+
+    // Runs until one of `conditions` is met, the program halts or an
+    // instruction fails.
+    pub fn run(&mut self, conditions : &StopConditions) -> StopReason
+    {
+        let last_step = conditions.max_steps.map(|max_steps| self.step_nb.saturating_add(max_steps));
+        let use_blocks = self.backend == Backend::BlockJit && conditions.is_coarse();
+        let mut recent_output = String::new();
+
+        loop
+        {
+            if last_step.is_some_and(|last_step| self.step_nb >= last_step)
+            {
+                return StopReason::StepBudgetExhausted;
+            }
+            if conditions.stop_on_input && self.needs_input()
+            {
+                return StopReason::InputNeeded;
+            }
+
+            let output_count = self.output_count;
+            let result =
+                if use_blocks
+                {
+                    let remaining = last_step.map_or(u64::MAX, |last_step| last_step - self.step_nb);
+                    self.run_block(remaining)
+                }
+                else
+                {
+                    self.step()
+                };
+
+            match result
+            {
+                Ok(_) => (),
+                Err(RunFailure::Halt) => return StopReason::Halted,
+                Err(RunFailure::InputNeeded) => return StopReason::InputNeeded,
+                Err(e) => return StopReason::Fault(e),
+            }
+
+            if let Some(ref pattern) = conditions.output_pattern
+            {
+                if self.output_count != output_count
+                {
+                    recent_output.push(self.last_output);
+                    if recent_output.ends_with(pattern.as_str())
+                    {
+                        return StopReason::OutputMatched(pattern.clone());
+                    }
+                    if recent_output.len() > 2 * pattern.len()
+                    {
+                        let excess = recent_output.len() - pattern.len();
+                        recent_output.drain(..excess);
+                    }
+                }
+            }
+            if conditions.breakpoints.contains(&self.program_counter)
+            {
+                return StopReason::PcReached(self.program_counter);
+            }
+            if conditions.predicate.as_ref().is_some_and(|predicate| predicate(self))
+            {
+                return StopReason::PredicateMatched;
+            }
+        }
+    }
+
+    fn run_block(&mut self, max_steps : u64) -> Result<(), RunFailure>
+    {
+        if self.print_debug
+        {
+            return self.step();
+        }
+
+        let program_counter = self.program_counter;
+        let block = match self.block_cache.get(program_counter)
+        {
+            Some(block) => block,
+            None =>
+            {
+                match jit::compile_block(&self.memory, program_counter)
+                {
+                    None => return self.step(),
+                    Some(block) =>
+                    {
+                        if let Some(ref mut code_watch) = self.code_watch
+                        {
+                            code_watch.on_decode(block.start, block.end.wrapping_sub(block.start));
+                        }
+                        let block = Arc::new(block);
+                        self.block_cache.insert(block.clone());
+                        block
+                    },
+                }
+            },
+        };
+
+        for instruction in block.instructions.iter().take(max_steps as usize)
+        {
+            let result = instruction(self);
+            self.count_step(&result);
+            result?;
+        }
+        Ok(())
+    }
+
+    fn decode(&mut self, address : u16) -> Result<OpCode, ReadOpCodeFailure>
+    {
+        if !self.use_decode_cache
+        {
+            return opcode::read_memory_to_op_code(&self.memory, address);
+        }
+
+        if let Some(op_code) = self.decode_cache.get(address)
+        {
+            return Ok(op_code);
+        }
+
+        let op_code_result = opcode::read_memory_to_op_code(&self.memory, address);
+        if let Ok(op_code) = op_code_result
+        {
+            self.decode_cache.insert(address, op_code);
+        }
+        op_code_result
+    }
+
     pub fn handle_op_code(&mut self, op_code : opcode::OpCode) ->
         Result<(), RunFailure>
     {
         if self.print_debug
         {
-            println!("{:?}", op_code);
-            println!("");
+            eprintln!("{:?}", op_code);
+            eprintln!();
         }
         match op_code
         {
@@ -175,64 +604,104 @@ impl VM
         }
     }
 
+    fn check_literal(&self, op_code : OpCode, value : u16) -> Result<u16, RunFailure>
+    {
+        if check_number(value).is_literal_value()
+        {
+            Ok(value)
+        }
+        else
+        {
+            Err(RunFailure::InvalidLiteral { program_counter : self.program_counter, op_code, value })
+        }
+    }
+
+    // Values loaded into a register must be numbers unless registers are
+    // read raw.
+    fn check_register_value(&self, op_code : OpCode, value : u16) -> Result<u16, RunFailure>
+    {
+        match self.conformance.register_read
+        {
+            RegisterRead::Chained => self.check_literal(op_code, value),
+            RegisterRead::Raw => Ok(value),
+        }
+    }
+
+    fn check_address(&self, op_code : OpCode, address : u16) -> Result<usize, RunFailure>
+    {
+        if check_number(address).is_literal_value() && (address as usize) < self.memory.len()
+        {
+            Ok(address as usize)
+        }
+        else
+        {
+            Err(RunFailure::InvalidAddress { program_counter : self.program_counter, op_code, address })
+        }
+    }
+
     fn handle_set_register(&mut self, set_register : opcode::SetRegister) ->
         Result<(), RunFailure>
     {
+        let op_code = OpCode::SetRegister(set_register);
+        let next = self.next_address(op_code)?;
         let actual_value = check_number(set_register.register);
-        let value_to_set = self.get_literal_value_or_register_value(set_register.value)?;
+        let value_to_set = self.get_literal_value_or_register_value(op_code, set_register.value)?;
         match actual_value
         {
             ParsedNumber::Register(r) =>
             {
-                assert!(check_number(value_to_set).is_literal_value());
                 self.register[r as usize] = value_to_set;
-                self.program_counter = self.program_counter + 3;
+                self.program_counter = next;
                 Ok(())
             },
-            _ => Err(RunFailure::InvalidValue)
+            _ => Err(self.invalid_value(op_code, set_register.register)),
         } 
     }
 
     fn handle_push(&mut self, push : opcode::Push) -> Result<(), RunFailure>
     {
-        let val = self.get_literal_value_or_register_value(push.value)?;
-        assert!(check_number(val).is_literal_value());
+        let op_code = OpCode::Push(push);
+        let next = self.next_address(op_code)?;
+        let val = self.get_literal_value_or_register_value(op_code, push.value)?;
         self.stack.push(val);
-        self.program_counter = self.program_counter + 2;
+        self.program_counter = next;
         Ok(())
     }
 
     fn handle_pop(&mut self, pop : opcode::Pop) -> Result<(), RunFailure>
     {
-        if self.stack.len() > 0
+        let op_code = OpCode::Pop(pop);
+        let next = self.next_address(op_code)?;
+        // The value is only taken off the stack once it is known to fit, a
+        // failed `pop` leaves the stack as it was.
+        match self.stack.last().cloned()
         {
-            let stack_value = self.stack.pop().unwrap();
-            let actual_value = check_number(pop.value);
-            match actual_value
+            Some(stack_value) =>
             {
-                ParsedNumber::Register(r) =>
+                let actual_value = check_number(pop.value);
+                match actual_value
                 {
-                    self.program_counter = self.program_counter + 2;
-                    assert!(check_number(stack_value).is_literal_value());
-                    self.register[r as usize] = stack_value;
-                    Ok(())
-                },
-                _ => Err(RunFailure::InvalidValue)
-            }
-        }
-        else
-        {
-            Err(RunFailure::CannotPopStackIsEmpty)
+                    ParsedNumber::Register(r) =>
+                    {
+                        let stack_value = self.check_register_value(op_code, stack_value)?;
+                        self.stack.pop();
+                        self.program_counter = next;
+                        self.register[r as usize] = stack_value;
+                        Ok(())
+                    },
+                    _ => Err(self.invalid_value(op_code, pop.value)),
+                }
+            },
+            None => Err(RunFailure::CannotPopStackIsEmpty),
         }
     }
 
     fn handle_is_equal(&mut self, is_equal : opcode::IsEqual) -> Result<(), RunFailure>
     {
-        let b = self.get_literal_value_or_register_value(is_equal.first_operand)?;
-        let c = self.get_literal_value_or_register_value(is_equal.second_operand)?;
-
-        assert!(check_number(b).is_literal_value());
-        assert!(check_number(c).is_literal_value());
+        let op_code = OpCode::IsEqual(is_equal);
+        let next = self.next_address(op_code)?;
+        let b = self.get_literal_value_or_register_value(op_code, is_equal.first_operand)?;
+        let c = self.get_literal_value_or_register_value(op_code, is_equal.second_operand)?;
 
         let actual_value = check_number(is_equal.cell_result);
         match actual_value
@@ -240,20 +709,19 @@ impl VM
            ParsedNumber::Register(r) =>
             {
                 self.register[r as usize] = if b == c { 1 } else { 0 };
-                self.program_counter = self.program_counter + 4;
+                self.program_counter = next;
                 Ok(())
             },
-            _ => Err(RunFailure::InvalidValue) 
+            _ => Err(self.invalid_value(op_code, is_equal.cell_result)),
         }
     }
 
     fn handle_greater_than(&mut self, is_greater_than : opcode::IsGreaterThan) -> Result<(), RunFailure>
     {
-        let b = self.get_literal_value_or_register_value(is_greater_than.first_operand)?;
-        let c = self.get_literal_value_or_register_value(is_greater_than.second_operand)?;
-
-        assert!(check_number(b).is_literal_value());
-        assert!(check_number(c).is_literal_value());
+        let op_code = OpCode::IsGreaterThan(is_greater_than);
+        let next = self.next_address(op_code)?;
+        let b = self.get_literal_value_or_register_value(op_code, is_greater_than.first_operand)?;
+        let c = self.get_literal_value_or_register_value(op_code, is_greater_than.second_operand)?;
 
         let actual_value = check_number(is_greater_than.cell_result);
         match actual_value
@@ -261,33 +729,45 @@ impl VM
            ParsedNumber::Register(r) =>
             {
                 self.register[r as usize] = if b > c { 1 } else { 0 };
-                self.program_counter = self.program_counter + 4;
+                self.program_counter = next;
                 Ok(())
             },
-            _ => Err(RunFailure::InvalidValue) 
+            _ => Err(self.invalid_value(op_code, is_greater_than.cell_result)),
+        }
+    }
+
+    fn jump_target(&self, op_code : OpCode, target : u16) -> Result<u16, RunFailure>
+    {
+        match self.conformance.jump_targets
+        {
+            JumpTargets::Literal if !check_number(target).is_literal_value() =>
+                Err(RunFailure::OpCodeParseFailure(ReadOpCodeFailure::InvalidOperandValue)),
+            _ => self.get_literal_value_or_register_value(op_code, target),
         }
     }
 
     fn handle_jump(&mut self, jump : opcode::Jump) -> Result<(), RunFailure>
     {
-        assert!(check_number(jump.value).is_literal_value());
-        self.program_counter = jump.value;
+        let op_code = OpCode::Jump(jump);
+        self.program_counter = self.jump_target(op_code, jump.value)?;
         Ok(())
     }
 
     fn handle_jump_not_zero(&mut self, jump_not_zero : opcode::JumpNotZero) -> 
         Result<(), RunFailure>
     {
-        let actual_value = self.get_literal_value_or_register_value(jump_not_zero.value)?;
-        assert!(check_number(actual_value).is_literal_value());
+        let op_code = OpCode::JumpNotZero(jump_not_zero);
+        let next = self.next_address(op_code)?;
+        let actual_value = self.get_literal_value_or_register_value(op_code, jump_not_zero.value)?;
+        let jump_location = self.jump_target(op_code, jump_not_zero.jump_location)?;
 
         if actual_value == 0 
         {
-            self.program_counter = self.program_counter + 3;
+            self.program_counter = next;
         }
         else
         {
-            self.program_counter = jump_not_zero.jump_location;
+            self.program_counter = jump_location;
         }
         Ok(())
     }
@@ -295,27 +775,28 @@ impl VM
     fn handle_jump_zero(&mut self, jump_zero : opcode::JumpZero) -> 
         Result<(), RunFailure>
     {
-        let actual_value = self.get_literal_value_or_register_value(jump_zero.value)?;
-        assert!(check_number(actual_value).is_literal_value());
+        let op_code = OpCode::JumpZero(jump_zero);
+        let next = self.next_address(op_code)?;
+        let actual_value = self.get_literal_value_or_register_value(op_code, jump_zero.value)?;
+        let jump_location = self.jump_target(op_code, jump_zero.jump_location)?;
 
         if actual_value != 0 
         {
-            self.program_counter = self.program_counter + 3;
+            self.program_counter = next;
         }
         else
         {
-            self.program_counter = jump_zero.jump_location;
+            self.program_counter = jump_location;
         }
         Ok(())
     }
 
     fn handle_add(&mut self, add : opcode::Add) -> Result<(), RunFailure>
     {
-        let b = self.get_literal_value_or_register_value(add.first_operand)? as u64;
-        let c = self.get_literal_value_or_register_value(add.second_operand)? as u64;
-
-        assert!(check_number(b as u16).is_literal_value());
-        assert!(check_number(c as u16).is_literal_value());
+        let op_code = OpCode::Add(add);
+        let next = self.next_address(op_code)?;
+        let b = self.get_literal_value_or_register_value(op_code, add.first_operand)? as u64;
+        let c = self.get_literal_value_or_register_value(op_code, add.second_operand)? as u64;
 
         let actual_value = check_number(add.cell_result);
         match actual_value
@@ -323,22 +804,20 @@ impl VM
             ParsedNumber::Register(r) =>
             {
                 let result = ((b + c) % 32768) as u16; // overflow ?
-                assert!(check_number(result).is_literal_value());
                 self.register[r as usize] = result;
-                self.program_counter = self.program_counter + 4;
+                self.program_counter = next;
                 Ok(())
             },
-            _ => Err(RunFailure::InvalidValue) 
+            _ => Err(self.invalid_value(op_code, add.cell_result)),
         }
     }
 
     fn handle_multiply(&mut self, multiply : opcode::Multiply) -> Result<(), RunFailure>
     {
-        let b = self.get_literal_value_or_register_value(multiply.first_operand)? as u64;
-        let c = self.get_literal_value_or_register_value(multiply.second_operand)? as u64;
-
-        assert!(check_number(b as u16).is_literal_value());
-        assert!(check_number(c as u16).is_literal_value());
+        let op_code = OpCode::Multiply(multiply);
+        let next = self.next_address(op_code)?;
+        let b = self.get_literal_value_or_register_value(op_code, multiply.first_operand)? as u64;
+        let c = self.get_literal_value_or_register_value(op_code, multiply.second_operand)? as u64;
 
         let actual_value = check_number(multiply.cell_result);
         match actual_value
@@ -346,22 +825,24 @@ impl VM
             ParsedNumber::Register(r) =>
             {
                 let result = ((b * c) % 32768) as u16;
-                assert!(check_number(result).is_literal_value());
                 self.register[r as usize] = result;
-                self.program_counter = self.program_counter + 4;
+                self.program_counter = next;
                 Ok(())
             },
-            _ => Err(RunFailure::InvalidValue) 
+            _ => Err(self.invalid_value(op_code, multiply.cell_result)),
         }
     }
 
     fn handle_modulo(&mut self, modulo : opcode::Modulo) -> Result<(), RunFailure>
     {
-        let b = self.get_literal_value_or_register_value(modulo.first_operand)? as u64;
-        let c = self.get_literal_value_or_register_value(modulo.second_operand)? as u64;
-
-        assert!(check_number(b as u16).is_literal_value());
-        assert!(check_number(c as u16).is_literal_value());
+        let op_code = OpCode::Modulo(modulo);
+        let next = self.next_address(op_code)?;
+        let b = self.get_literal_value_or_register_value(op_code, modulo.first_operand)? as u64;
+        let c = self.get_literal_value_or_register_value(op_code, modulo.second_operand)? as u64;
+        if c == 0
+        {
+            return Err(RunFailure::DivisionByZero { program_counter : self.program_counter, op_code });
+        }
 
         let actual_value = check_number(modulo.cell_result);
         match actual_value
@@ -369,22 +850,20 @@ impl VM
             ParsedNumber::Register(r) =>
             {
                 let result = ((b % c) % 32768) as u16;
-                assert!(check_number(result).is_literal_value());
                 self.register[r as usize] = result;
-                self.program_counter = self.program_counter + 4;
+                self.program_counter = next;
                 Ok(())
             },
-            _ => Err(RunFailure::InvalidValue) 
+            _ => Err(self.invalid_value(op_code, modulo.cell_result)),
         }
     }
 
     fn handle_and(&mut self, add : opcode::And) -> Result<(), RunFailure>
     {
-        let b = self.get_literal_value_or_register_value(add.first_operand)?;
-        let c = self.get_literal_value_or_register_value(add.second_operand)?;
-
-        assert!(check_number(b).is_literal_value());
-        assert!(check_number(c).is_literal_value());
+        let op_code = OpCode::And(add);
+        let next = self.next_address(op_code)?;
+        let b = self.get_literal_value_or_register_value(op_code, add.first_operand)?;
+        let c = self.get_literal_value_or_register_value(op_code, add.second_operand)?;
 
         let actual_value = check_number(add.cell_result);
         match actual_value
@@ -392,22 +871,20 @@ impl VM
             ParsedNumber::Register(r) =>
             {
                 let result =  b & c;
-                assert!(check_number(result).is_literal_value());
                 self.register[r as usize] = result;
-                self.program_counter = self.program_counter + 4;
+                self.program_counter = next;
                 Ok(())
             },
-            _ => Err(RunFailure::InvalidValue) 
+            _ => Err(self.invalid_value(op_code, add.cell_result)),
         }
     }
 
     fn handle_or(&mut self, or : opcode::Or) -> Result<(), RunFailure>
     {
-        let b = self.get_literal_value_or_register_value(or.first_operand)?;
-        let c = self.get_literal_value_or_register_value(or.second_operand)?;
-
-        assert!(check_number(b).is_literal_value());
-        assert!(check_number(c).is_literal_value());
+        let op_code = OpCode::Or(or);
+        let next = self.next_address(op_code)?;
+        let b = self.get_literal_value_or_register_value(op_code, or.first_operand)?;
+        let c = self.get_literal_value_or_register_value(op_code, or.second_operand)?;
 
         let actual_value = check_number(or.cell_result);
         match actual_value
@@ -415,20 +892,20 @@ impl VM
             ParsedNumber::Register(r) =>
             {
                 let result =  b | c;
-                assert!(check_number(result).is_literal_value());
                 self.register[r as usize] = result;
-                self.program_counter = self.program_counter + 4;
+                self.program_counter = next;
                 Ok(())
             },
-            _ => Err(RunFailure::InvalidValue) 
+            _ => Err(self.invalid_value(op_code, or.cell_result)),
         }
     }
 
     fn handle_not(&mut self, not : opcode::Not) -> Result<(), RunFailure>
     {
-        let val = self.get_literal_value_or_register_value(not.operand)?;
+        let op_code = OpCode::Not(not);
+        let next = self.next_address(op_code)?;
+        let val = self.get_literal_value_or_register_value(op_code, not.operand)?;
 
-        assert!(check_number(val).is_literal_value());
 
         let actual_value = check_number(not.cell_result);
         match actual_value
@@ -436,18 +913,19 @@ impl VM
             ParsedNumber::Register(r) =>
             {
                 let result = (!val) & 0b0111_1111_1111_1111;
-                assert!(check_number(result).is_literal_value());
                 self.register[r as usize] = result;
-                self.program_counter = self.program_counter + 3;
+                self.program_counter = next;
                 Ok(())
             },
-            _ => Err(RunFailure::InvalidValue) 
+            _ => Err(self.invalid_value(op_code, not.cell_result)),
         }
     }
 
     fn handle_read_memory(&mut self, read_memory : opcode::ReadMemory) 
         -> Result<(), RunFailure>
     {
+        let op_code = OpCode::ReadMemory(read_memory);
+        let next = self.next_address(op_code)?;
         let mem_cell = check_number(read_memory.memory_address_to_read);
         let actual_value = check_number(read_memory.cell_result);
         match actual_value
@@ -458,119 +936,204 @@ impl VM
                 {
                     ParsedNumber::Register(r) =>
                     {
-                        let mem_address = self.register[r as usize];
-                        if !(check_number(mem_address).is_literal_value())
-                        {
-                            panic!("Error in read memory implementation");
-                        }
-                        let value = self.memory[mem_address as usize];
-                        assert!(check_number(value).is_literal_value());
+                        let mem_address = self.check_address(op_code, self.register[r as usize])?;
+                        let value = self.check_register_value(op_code, self.memory[mem_address])?;
                         self.register[r_dest as usize] = value;
-                        self.program_counter = self.program_counter + 3;
+                        self.program_counter = next;
                         Ok(())
                     },
                     ParsedNumber::LiteralValue(val) =>
                     {
-                        let value = self.memory[val as usize];
-                        assert!(check_number(value).is_literal_value());
+                        let mem_address = self.check_address(op_code, val)?;
+                        let value = self.check_register_value(op_code, self.memory[mem_address])?;
                         self.register[r_dest as usize] = value;
-                        self.program_counter = self.program_counter + 3;
+                        self.program_counter = next;
                         Ok(())
                     },
-                    _ => Err(RunFailure::InvalidValue) 
-
+                    _ => Err(self.invalid_value(op_code, read_memory.memory_address_to_read)),
                 }
             },
-            _ => Err(RunFailure::InvalidValue) 
+            _ => Err(self.invalid_value(op_code, read_memory.cell_result)),
+        }
+    }
+
+    // Every memory write, from the program or from tools, ends up here so
+    // that translated code and decoded instructions never go stale.
+    fn store(&mut self, address : u16, value : u16)
+    {
+        self.memory.write(address as usize, value);
+        self.decode_cache.invalidate(address);
+        self.block_cache.invalidate(address);
+    }
+
+    // A `wmem`, the code watch sees it unlike writes from outside.
+    fn store_from_program(&mut self, address : u16, value : u16)
+    {
+        if let Some(ref mut code_watch) = self.code_watch
+        {
+            let old = self.memory.get(address as usize).unwrap_or(0);
+            code_watch.on_write(self.step_nb, self.program_counter, address, old, value);
+        }
+        self.store(address, value);
+    }
+
+    // Writes a word from outside of the program, e.g. a debugger. The
+    // address is checked like a `wmem` to it would be.
+    pub fn write_memory(&mut self, address : u16, value : u16) -> Result<(), RunFailure>
+    {
+        let op_code = OpCode::WriteMemory(opcode::WriteMemory { memory_address_to_write_to : address, value });
+        self.check_address(op_code, address)?;
+        self.store(address, value);
+        Ok(())
+    }
+
+    // Patches a word the way a `wmem` at the current program counter would,
+    // so that the code watch sees patched code.
+    pub fn patch_memory(&mut self, address : u16, value : u16) -> Result<(), RunFailure>
+    {
+        let op_code = OpCode::WriteMemory(opcode::WriteMemory { memory_address_to_write_to : address, value });
+        self.check_address(op_code, address)?;
+        self.store_from_program(address, value);
+        Ok(())
+    }
+
+    // Sets a register from outside of the program, e.g. a debugger. Only
+    // numbers are accepted unless registers are read raw.
+    pub fn set_register(&mut self, r : usize, value : u16) -> Result<(), RunFailure>
+    {
+        match self.conformance.register_read
+        {
+            RegisterRead::Chained if !check_number(value).is_literal_value() =>
+                Err(RunFailure::InvalidRegisterValue { register : r, value }),
+            _ =>
+            {
+                self.register[r] = value;
+                Ok(())
+            },
         }
     }
 
+    // Pushes a word from outside of the program, e.g. to rebuild a state.
+    pub fn push_stack(&mut self, value : u16)
+    {
+        self.stack.push(value);
+    }
+
+    pub fn set_program_counter(&mut self, program_counter : u16)
+    {
+        self.program_counter = program_counter;
+    }
+
     fn handle_write_memory(&mut self, write_memory: opcode::WriteMemory)
         -> Result<(), RunFailure>
     {
+        let op_code = OpCode::WriteMemory(write_memory);
+        let next = self.next_address(op_code)?;
         let memory_address_to_write_to = check_number(write_memory.memory_address_to_write_to);
-        let value_to_write = self.get_literal_value_or_register_value(write_memory.value)?;
+        let value_to_write = self.get_literal_value_or_register_value(op_code, write_memory.value)?;
         //print!("{}", (value_to_write as u8) as char);
-        assert!(check_number(value_to_write).is_literal_value());
         match memory_address_to_write_to
         {
             ParsedNumber::Register(r) =>
             {
                 let mem_address = self.register[r as usize];
-                if !(check_number(mem_address).is_literal_value())
-                {
-                    panic!("Error in write memory implementation");
-                }
-                self.memory[mem_address as usize] = value_to_write;
-                self.program_counter = self.program_counter + 3;
+                self.check_address(op_code, mem_address)?;
+                self.store_from_program(mem_address, value_to_write);
+                self.program_counter = next;
                 Ok(())
             },
             ParsedNumber::LiteralValue(val) =>
             {
-                if !(check_number(self.memory[val as usize]).is_literal_value())
-                {
-                    panic!("Error in write memory implementation");
-                }
-                self.memory[val as usize] = value_to_write;
-                self.program_counter = self.program_counter + 3;
+                self.check_address(op_code, val)?;
+                self.store_from_program(val, value_to_write);
+                self.program_counter = next;
                 Ok(())
             },
-            _ => Err(RunFailure::InvalidValue) 
+            _ => Err(self.invalid_value(op_code, write_memory.memory_address_to_write_to)),
         }
     }
 
     fn handle_call(&mut self, call : opcode::Call) -> Result<(), RunFailure>
     {
-        self.stack.push(self.program_counter + 2);
-        let actual_value = self.get_literal_value_or_register_value(call.value)?;
-        assert!(check_number(actual_value).is_literal_value());
+        let op_code = OpCode::Call(call);
+        let next = self.next_address(op_code)?;
+        let actual_value = self.get_literal_value_or_register_value(op_code, call.value)?;
+        let call_site = Call { caller : self.program_counter, entry : actual_value };
+        self.stack.push_return(next, call_site);
         self.program_counter = actual_value;
         Ok(())
     }
 
     fn handle_return(&mut self) -> Result<(), RunFailure>
     {
-        if self.stack.len() < 1
-        {
-            Err(RunFailure::CannotReturnStackIsEmpty)
-        }
-        else
+        match self.stack.last().cloned()
         {
-            let return_address = self.stack.pop().unwrap();
-            assert!(check_number(return_address).is_literal_value());
-            self.program_counter = return_address;
-            Ok(())
+            Some(return_address) =>
+            {
+                self.program_counter = self.check_literal(OpCode::Return, return_address)?;
+                self.stack.pop();
+                Ok(())
+            },
+            None =>
+            {
+                match self.conformance.empty_return
+                {
+                    EmptyReturn::Fail => Err(RunFailure::CannotReturnStackIsEmpty),
+                    EmptyReturn::Halt => Err(RunFailure::Halt),
+                }
+            },
         }
     }
 
     fn handle_out(&mut self, out : opcode::Out) -> Result<(), RunFailure>
     {
-        let actual_value = self.get_literal_value_or_register_value(out.value)?;
-        assert!(check_number(actual_value).is_literal_value());
-        print!("{}", (actual_value as u8) as char);
-        self.program_counter = self.program_counter + 2;
+        let op_code = OpCode::Out(out);
+        let next = self.next_address(op_code)?;
+        let actual_value = self.get_literal_value_or_register_value(op_code, out.value)?;
+        let c = (actual_value as u8) as char;
+        self.output.write_char(c);
+        self.output_count += 1;
+        self.last_output = c;
+        if let Some(ref mut harvester) = self.code_harvester
+        {
+            harvester.on_output_char(c, self.step_nb);
+        }
+        if let Some(ref mut transcript) = self.transcript
+        {
+            transcript.on_output_char(c, self.step_nb);
+        }
+        self.program_counter = next;
         Ok(())
     }
 
     fn handle_in(&mut self, in_arg : opcode::In) -> Result<(), RunFailure>
     {
+        let op_code = OpCode::In(in_arg);
+        let next = self.next_address(op_code)?;
         let actual_value = check_number(in_arg.value);
 
-        if self.pending_char.len() == 0
+        if self.pending_char.is_empty()
         {
-            let mut line = String::new();
-
-            io::stdin()
-                .read_line(&mut line)
-                .ok()
-                .expect("Failed to read line");
+            let line = match self.input.read_line()
+            {
+                None => return Err(RunFailure::InputNeeded),
+                Some(line) => line,
+            };
 
-            if line.contains("dump")
+            if self.handle_tool_command(&line)
             {
-                self.dump_state();
                 return self.handle_in(in_arg);
             }
 
+            if let Some(ref mut harvester) = self.code_harvester
+            {
+                harvester.on_input_line(&line);
+            }
+            if let Some(ref mut transcript) = self.transcript
+            {
+                transcript.on_input_line(&line, self.step_nb);
+            }
+
             let str_as_bytes : &[u8] = line.as_bytes();
             let mut cpy = Vec::new();
 
@@ -585,11 +1148,11 @@ impl VM
         {
             ParsedNumber::Register(r) =>
             {
-                if self.pending_char.len() >= 1
+                if !self.pending_char.is_empty()
                 {
                     let byte = self.pending_char.pop().unwrap();
                     self.register[r as usize] = byte as u16;
-                    self.program_counter = self.program_counter + 2;
+                    self.program_counter = next;
                     Ok(())
                 }
                 else
@@ -597,93 +1160,238 @@ impl VM
                     Err(RunFailure::InvalidInput)
                 }
             },
-            _ => Err(RunFailure::InvalidValue),
+            _ => Err(self.invalid_value(op_code, in_arg.value)),
         }
     }
 
     fn handle_noop(&mut self) -> Result<(), RunFailure>
     {
-        self.program_counter = self.program_counter + 1;
+        self.program_counter = self.next_address(OpCode::Noop)?;
         Ok(())
     }
 
+    pub fn game_layout(&self) -> Option<GameLayout>
+    {
+        GameLayout::locate(&self.memory)
+    }
+
+    pub fn current_room(&self) -> Option<RoomInfo>
+    {
+        self.game_layout().and_then(|layout| layout.current_room(&self.memory))
+    }
+
+    pub fn inventory(&self) -> Vec<Item>
+    {
+        self.game_layout().map(|layout| layout.inventory(&self.memory)).unwrap_or_default()
+    }
+
+    pub fn item_positions(&self) -> Vec<Item>
+    {
+        self.game_layout().map(|layout| layout.items(&self.memory)).unwrap_or_default()
+    }
+
+    // Commands typed at the prompt that are handled by the VM itself
+    // instead of being passed to the program.
+    fn handle_tool_command(&mut self, line : &str) -> bool
+    {
+        let command = line.trim();
+        if command.split_whitespace().next() == Some("dump")
+        {
+            self.dump_state();
+            true
+        }
+        else if command == "state"
+        {
+            let text = match self.game_layout()
+            {
+                None => "cannot locate game state in memory\n".to_owned(),
+                Some(layout) => layout.describe(&self.memory),
+            };
+            self.print(&text);
+            true
+        }
+        else if command == "mem" || command.starts_with("mem ")
+        {
+            let mut memory_view = mem::take(&mut self.memory_view);
+            let text = match memory_view.execute(self, &command[3..])
+            {
+                Ok(text) => text,
+                Err(e) => format!("{:?}\n", e),
+            };
+            self.memory_view = memory_view;
+            self.print(&text);
+            true
+        }
+        else if let Some(file_name) = command.strip_prefix("export ")
+        {
+            let text = match loader::write_image_file(file_name.trim(), &self.memory.to_vec())
+            {
+                Ok(()) => format!("memory written to {}\n", file_name.trim()),
+                Err(e) => format!("cannot write {} {}\n", file_name.trim(), e),
+            };
+            self.print(&text);
+            true
+        }
+        else if command == "imagediff" || command.starts_with("imagediff ")
+        {
+            let file_name = Some(command["imagediff".len()..].trim()).filter(|name| !name.is_empty()).unwrap_or("challenge.bin");
+            let text = match loader::read_image_file(file_name)
+            {
+                Ok(image) => image_diff::describe(&image_diff::diff(&image, &self.memory.to_vec())),
+                Err(e) => format!("cannot read {} {:?}\n", file_name, e),
+            };
+            self.print(&text);
+            true
+        }
+        else if command == "smc"
+        {
+            let text = match self.code_watch
+            {
+                None => "code watch is off\n".to_owned(),
+                Some(ref code_watch) => code_watch.report(),
+            };
+            self.print(&text);
+            true
+        }
+        else if command == "explore"
+        {
+            self.explore_world();
+            true
+        }
+        else if let Some(target) = command.strip_prefix("plan room ")
+        {
+            self.plan_route(&planner::Target::Room(target.trim().to_owned()));
+            true
+        }
+        else if let Some(target) = command.strip_prefix("plan item ")
+        {
+            self.plan_route(&planner::Target::Item(target.trim().to_owned()));
+            true
+        }
+        else
+        {
+            false
+        }
+    }
+
+    fn explore(&mut self) -> Option<WorldGraph>
+    {
+        self.print("exploring...\n");
+        match Explorer::new().explore(self)
+        {
+            Err(e) =>
+            {
+                self.print(&format!("cannot explore world {:?}\n", e));
+                None
+            },
+            Ok(graph) =>
+            {
+                self.print(&format!("found {} rooms\n", graph.rooms.len()));
+                Some(graph)
+            },
+        }
+    }
+
+    fn explore_world(&mut self)
+    {
+        if let Some(graph) = self.explore()
+        {
+            if let Err(e) = fs::write("world.dot", graph.to_dot())
+            {
+                self.print(&format!("cannot write world.dot {}\n", e));
+            }
+            if let Err(e) = fs::write("world.json", graph.to_json())
+            {
+                self.print(&format!("cannot write world.json {}\n", e));
+            }
+        }
+    }
+
+    fn plan_route(&mut self, target : &planner::Target)
+    {
+        if let Some(graph) = self.explore()
+        {
+            let text = match planner::plan(&graph, self, target)
+            {
+                Err(e) => format!("cannot plan route {:?}\n", e),
+                Ok(commands) =>
+                {
+                    let script = planner::to_script(&commands);
+                    match fs::write("plan.txt", &script)
+                    {
+                        Ok(()) => script,
+                        Err(e) => format!("{}cannot write plan.txt {}\n", script, e),
+                    }
+                },
+            };
+            self.print(&text);
+        }
+    }
+
     fn dump_state(&mut self)
     {
-        println!("============");
-        self.print_debug();
-        println!("============");
+        let text = format!("============\n{}============\n", self.debug_text());
+        self.print(&text);
         let dt = chrono::Local::now();
         let dt_str = dt.format("%Y-%m-%d--%H-%M-%S").to_string();
         let dir = "dump/".to_owned() + &dt_str + "/";
-        println!("{}", dir);
+        let text = format!("{}\nstate hash {:016x}\n", dir, self.state_hash());
+        self.print(&text);
         let result = fs::create_dir_all(&dir);
         match result
         {
-            Err(e) => println!("cannot dump machine {}", e),
+            Err(e) => self.print(&format!("cannot dump machine {}\n", e)),
             Ok(_) =>
             {
                 {
-                    let mut f = File::create(dir.clone() + "registers.txt");
-                    match f
+                    let f = File::create(dir.clone() + "registers.txt");
+                    if let Ok(mut file) = f
                     {
-                        Ok(mut file) =>
+                        for reg in self.register.iter()
                         {
-                            for reg in self.register.iter()
-                            {
-                                file.write_all(format!("{}\n", reg).as_bytes()).unwrap();
-                            }
-                        },
-                        Err(_) => (),
+                            file.write_all(format!("{}\n", reg).as_bytes()).unwrap();
+                        }
                     }
                 }
                 {
-                    let mut f = File::create(dir.clone() + "stack.txt");
-                    match f
+                    let f = File::create(dir.clone() + "stack.txt");
+                    if let Ok(mut file) = f
                     {
-                        Ok(mut file) =>
+                        for stack_value in self.stack.iter()
                         {
-                            for stack_value in self.stack.iter()
-                            {
-                                file.write_all(format!("{}\n", stack_value).as_bytes()).unwrap();
-                            }
-                        },
-                        Err(_) => (),
+                            file.write_all(format!("{}\n", stack_value).as_bytes()).unwrap();
+                        }
                     }
                 }
                 {
-                    let mut f = File::create(dir.clone() + "program_counter.txt");
-                    match f
+                    let f = File::create(dir.clone() + "program_counter.txt");
+                    if let Ok(mut file) = f
                     {
-                        Ok(mut file) =>
-                        {
-                            file.write_all(format!("{}\n", self.program_counter).as_bytes()).unwrap();
-                        },
-                        Err(_) => (),
+                        file.write_all(format!("{}\n", self.program_counter).as_bytes()).unwrap();
                     }
                 }
                 {
-                    let mut f = File::create(dir.clone() + "step_number.txt");
-                    match f
+                    let f = File::create(dir.clone() + "step_number.txt");
+                    if let Ok(mut file) = f
                     {
-                        Ok(mut file) =>
-                        {
-                            file.write_all(format!("{}\n", self.step_nb).as_bytes()).unwrap();
-                        },
-                        Err(_) => (),
+                        file.write_all(format!("{}\n", self.step_nb).as_bytes()).unwrap();
                     }
                 }
                 {
-                    let mut f = File::create(dir.clone() + "memory.txt");
-                    match f
+                    let f = File::create(dir.clone() + "state_hash.txt");
+                    if let Ok(mut file) = f
                     {
-                        Ok(mut file) =>
+                        file.write_all(format!("{:016x}\n", self.state_hash()).as_bytes()).unwrap();
+                    }
+                }
+                {
+                    let f = File::create(dir.clone() + "memory.txt");
+                    if let Ok(mut file) = f
+                    {
+                        for mem_value in self.memory.iter()
                         {
-                            for mem_value in self.memory.iter()
-                            {
-                                file.write_all(format!("{}\n", mem_value).as_bytes()).unwrap();
-                            }
-                        },
-                        Err(_) => (),
+                            file.write_all(format!("{}\n", mem_value).as_bytes()).unwrap();
+                        }
                     }
                 }
             },
@@ -0,0 +1,377 @@
+extern crate synacor_challenge;
+
+mod common;
+
+use common::{run, run_with_input, R0, R1, R2, R3, R7};
+use synacor_challenge::jit::Backend;
+use synacor_challenge::opcode::ReadOpCodeFailure;
+use synacor_challenge::run_until::{StopConditions, StopReason};
+use synacor_challenge::stack::{Call, Frame};
+use synacor_challenge::vm::{VM, RunFailure};
+
+fn assert_halted(reason : &StopReason)
+{
+    assert!(matches!(*reason, StopReason::Halted), "unexpected stop {:?}", reason);
+}
+
+#[test]
+fn halt()
+{
+    let run = run(&[0, 21]);
+    assert_halted(&run.reason);
+    assert_eq!(run.vm.program_counter(), 0);
+}
+
+#[test]
+fn set()
+{
+    // set r0 42; set r1 r0; halt
+    let run = run(&[1, R0, 42, 1, R1, R0, 0]);
+    assert_halted(&run.reason);
+    assert_eq!(run.vm.registers()[0], 42);
+    assert_eq!(run.vm.registers()[1], 42);
+}
+
+#[test]
+fn push_and_pop()
+{
+    // set r0 5; push r0; push 9; pop r1; pop r2; halt
+    let run = run(&[1, R0, 5, 2, R0, 2, 9, 3, R1, 3, R2, 0]);
+    assert_halted(&run.reason);
+    assert_eq!(run.vm.registers()[1], 9);
+    assert_eq!(run.vm.registers()[2], 5);
+    assert!(run.vm.stack().is_empty());
+}
+
+#[test]
+fn eq()
+{
+    // eq r0 3 3; eq r1 3 4; halt
+    let run = run(&[4, R0, 3, 3, 4, R1, 3, 4, 0]);
+    assert_halted(&run.reason);
+    assert_eq!(run.vm.registers()[0], 1);
+    assert_eq!(run.vm.registers()[1], 0);
+}
+
+#[test]
+fn gt()
+{
+    // gt r0 4 3; gt r1 3 3; gt r2 3 4; halt
+    let run = run(&[5, R0, 4, 3, 5, R1, 3, 3, 5, R2, 3, 4, 0]);
+    assert_halted(&run.reason);
+    assert_eq!(&run.vm.registers()[..3], &[1, 0, 0]);
+}
+
+#[test]
+fn jmp()
+{
+    // jmp 5; set r0 9; set r1 1; halt
+    let run = run(&[6, 5, 1, R0, 9, 1, R1, 1, 0]);
+    assert_halted(&run.reason);
+    assert_eq!(run.vm.registers()[0], 0);
+    assert_eq!(run.vm.registers()[1], 1);
+}
+
+// The decoder only accepts literal jump targets.
+#[test]
+fn jump_targets_must_be_literals()
+{
+    for program in &[[6, R0, 0, 0], [7, 1, R0, 0], [8, 0, R0, 0]]
+    {
+        let run = run(program);
+        assert!
+        (
+            matches!(run.reason, StopReason::Fault(RunFailure::OpCodeParseFailure(ReadOpCodeFailure::InvalidOperandValue)))
+        );
+    }
+}
+
+#[test]
+fn jt()
+{
+    // jt 1 6; set r0 9; jt 0 12; set r1 1; halt
+    let run = run(&[7, 1, 6, 1, R0, 9, 7, 0, 12, 1, R1, 1, 0]);
+    assert_halted(&run.reason);
+    assert_eq!(run.vm.registers()[0], 0);
+    assert_eq!(run.vm.registers()[1], 1);
+}
+
+#[test]
+fn jf()
+{
+    // jf 0 6; set r0 9; jf 1 12; set r1 1; halt
+    let run = run(&[8, 0, 6, 1, R0, 9, 8, 1, 12, 1, R1, 1, 0]);
+    assert_halted(&run.reason);
+    assert_eq!(run.vm.registers()[0], 0);
+    assert_eq!(run.vm.registers()[1], 1);
+}
+
+#[test]
+fn jt_and_jf_read_registers()
+{
+    // set r0 1; jt r0 9; halt; jf r1 13; halt; set r2 1; halt
+    let run = run(&[1, R0, 1, 7, R0, 7, 0, 8, R1, 11, 0, 1, R2, 1, 0]);
+    assert_halted(&run.reason);
+    assert_eq!(run.vm.registers()[2], 1);
+}
+
+#[test]
+fn add()
+{
+    // add r0 3 4; halt
+    let run = run(&[9, R0, 3, 4, 0]);
+    assert_halted(&run.reason);
+    assert_eq!(run.vm.registers()[0], 7);
+}
+
+#[test]
+fn add_wraps_modulo_32768()
+{
+    // set r0 32758; add r1 r0 15; add r2 32767 32767; halt
+    let run = run(&[1, R0, 32758, 9, R1, R0, 15, 9, R2, 32767, 32767, 0]);
+    assert_halted(&run.reason);
+    assert_eq!(run.vm.registers()[1], 5);
+    assert_eq!(run.vm.registers()[2], 32766);
+}
+
+#[test]
+fn mult()
+{
+    // mult r0 6 7; halt
+    let run = run(&[10, R0, 6, 7, 0]);
+    assert_halted(&run.reason);
+    assert_eq!(run.vm.registers()[0], 42);
+}
+
+#[test]
+fn mult_wraps_modulo_32768()
+{
+    // mult r0 16384 4; mult r1 200 200; mult r2 32767 32767; halt
+    let run = run(&[10, R0, 16384, 4, 10, R1, 200, 200, 10, R2, 32767, 32767, 0]);
+    assert_halted(&run.reason);
+    assert_eq!(run.vm.registers()[0], 0);
+    assert_eq!(run.vm.registers()[1], 40000 % 32768);
+    assert_eq!(run.vm.registers()[2], 1);
+}
+
+#[test]
+fn modulo()
+{
+    // mod r0 17 5; mod r1 4 5; halt
+    let run = run(&[11, R0, 17, 5, 11, R1, 4, 5, 0]);
+    assert_halted(&run.reason);
+    assert_eq!(run.vm.registers()[0], 2);
+    assert_eq!(run.vm.registers()[1], 4);
+}
+
+#[test]
+fn and_or()
+{
+    // and r0 12 10; or r1 12 10; halt
+    let run = run(&[12, R0, 12, 10, 13, R1, 12, 10, 0]);
+    assert_halted(&run.reason);
+    assert_eq!(run.vm.registers()[0], 8);
+    assert_eq!(run.vm.registers()[1], 14);
+}
+
+#[test]
+fn not_keeps_15_bits()
+{
+    // not r0 0; not r1 32767; not r2 21845; halt
+    let run = run(&[14, R0, 0, 14, R1, 32767, 14, R2, 21845, 0]);
+    assert_halted(&run.reason);
+    assert_eq!(run.vm.registers()[0], 32767);
+    assert_eq!(run.vm.registers()[1], 0);
+    assert_eq!(run.vm.registers()[2], 10922);
+}
+
+#[test]
+fn rmem_and_wmem()
+{
+    // wmem 16 1234; rmem r0 16; set r1 17; wmem r1 77; rmem r2 r1; halt; 16: 0 0
+    let run = run(&[16, 16, 1234, 15, R0, 16, 1, R1, 17, 16, R1, 77, 15, R2, R1, 0, 0, 0]);
+    assert_halted(&run.reason);
+    assert_eq!(run.vm.registers()[0], 1234);
+    assert_eq!(run.vm.registers()[2], 77);
+    assert_eq!(run.vm.memory()[16], 1234);
+    assert_eq!(run.vm.memory()[17], 77);
+}
+
+#[test]
+fn wmem_rewrites_code()
+{
+    // wmem 5 42; set r0 9; halt -- the write turns `set r0 9` into `set r0 42`
+    let run = run(&[16, 5, 42, 1, R0, 9, 0]);
+    assert_halted(&run.reason);
+    assert_eq!(run.vm.registers()[0], 42);
+}
+
+#[test]
+fn wmem_rewrites_code_that_already_ran()
+{
+    // 0: add r0 r0 1; gt r1 r0 3; jt r1 16; wmem 3 5; jmp 0; 16: halt
+    // The first pass decodes and translates the `add`, the write then turns
+    // it into `add r0 r0 5`. Stale code would stop at 4 instead of 6.
+    let run = run(&[9, R0, R0, 1, 5, R1, R0, 3, 7, R1, 16, 16, 3, 5, 6, 0, 0]);
+    assert_halted(&run.reason);
+    assert_eq!(run.vm.registers()[0], 6);
+    assert_eq!(run.vm.step_nb(), 9);
+}
+
+#[test]
+fn execution_stops_at_the_end_of_the_address_space()
+{
+    // noop; noop; noop in the last three words, the last one runs off the end
+    let mut program = vec!(0; 65536);
+    program[65533..].copy_from_slice(&[21, 21, 21]);
+    for &backend in &[Backend::Interpreter, Backend::BlockJit]
+    {
+        let mut vm = VM::new(program.clone());
+        vm.set_backend(backend);
+        vm.set_program_counter(65533);
+        match vm.run(&StopConditions::new().max_steps(3))
+        {
+            StopReason::Fault(RunFailure::EndOfMemory { program_counter, .. }) => assert_eq!(program_counter, 65535),
+            reason => panic!("unexpected stop {:?}", reason),
+        }
+    }
+}
+
+#[test]
+fn call_and_ret()
+{
+    // call 4; halt; noop; set r0 1; ret
+    let run = run(&[17, 4, 0, 21, 1, R0, 1, 18]);
+    assert_halted(&run.reason);
+    assert_eq!(run.vm.registers()[0], 1);
+    assert_eq!(run.vm.program_counter(), 2);
+    assert!(run.vm.stack().is_empty());
+}
+
+#[test]
+fn call_register()
+{
+    // set r7 7; call r7; halt; set r0 1; ret
+    let run = run(&[1, R7, 7, 17, R7, 0, 21, 1, R0, 1, 18]);
+    assert_halted(&run.reason);
+    assert_eq!(run.vm.registers()[0], 1);
+    assert_eq!(run.vm.program_counter(), 5);
+}
+
+#[test]
+fn backtrace_separates_return_addresses()
+{
+    // push 1; call 6; halt; noop; push 2; set r7 13; call r7; push 3; halt
+    let run = run(&[2, 1, 17, 6, 0, 21, 2, 2, 1, R7, 13, 17, R7, 2, 3, 0]);
+    assert_halted(&run.reason);
+    assert_eq!(run.vm.stack(), [1, 4, 2, 13, 3]);
+    assert_eq!
+    (
+        run.vm.backtrace(),
+        [
+            Frame { call : Some(Call { caller : 11, entry : 13 }), base : 4, data : vec!(3) },
+            Frame { call : Some(Call { caller : 2, entry : 6 }), base : 2, data : vec!(2) },
+            Frame { call : None, base : 0, data : vec!(1) },
+        ]
+    );
+}
+
+#[test]
+fn out()
+{
+    // set r0 'i'; out 'h'; out r0; out '\n'; halt
+    let run = run(&[1, R0, 'i' as u16, 19, 'h' as u16, 19, R0, 19, 10, 0]);
+    assert_halted(&run.reason);
+    assert_eq!(run.output, "hi\n");
+}
+
+#[test]
+fn in_reads_one_character_at_a_time()
+{
+    // in r0; in r1; in r2; in r3; halt
+    let run = run_with_input(&[20, R0, 20, R1, 20, R2, 20, R3, 0], &["ab", "c"]);
+    assert_halted(&run.reason);
+    assert_eq!(&run.vm.registers()[..4], &['a' as u16, 'b' as u16, 10, 'c' as u16]);
+}
+
+#[test]
+fn in_without_input_stops()
+{
+    // out '>'; in r0; halt
+    let run = run(&[19, '>' as u16, 20, R0, 0]);
+    assert!(matches!(run.reason, StopReason::InputNeeded));
+    assert_eq!(run.vm.program_counter(), 2);
+    assert_eq!(run.vm.step_nb(), 1);
+    assert_eq!(run.output, ">");
+}
+
+#[test]
+fn noop()
+{
+    let run = run(&[21, 21, 0]);
+    assert_halted(&run.reason);
+    assert_eq!(run.vm.program_counter(), 2);
+}
+
+#[test]
+fn pop_with_empty_stack_fails()
+{
+    let run = run(&[3, R0]);
+    assert!(matches!(run.reason, StopReason::Fault(RunFailure::CannotPopStackIsEmpty)));
+}
+
+#[test]
+fn modulo_by_zero_fails()
+{
+    // set r0 0; mod r1 5 r0
+    let run = run(&[1, R0, 0, 11, R1, 5, R0]);
+    match run.reason
+    {
+        StopReason::Fault(RunFailure::DivisionByZero { program_counter, .. }) => assert_eq!(program_counter, 3),
+        reason => panic!("unexpected stop {:?}", reason),
+    }
+}
+
+#[test]
+fn literal_destination_is_rejected()
+{
+    // set 5 5
+    let run = run(&[1, 5, 5]);
+    assert!
+    (
+        matches!(run.reason, StopReason::Fault(RunFailure::OpCodeParseFailure(ReadOpCodeFailure::InvalidOperandValue)))
+    );
+}
+
+#[test]
+fn invalid_number_operand_is_rejected()
+{
+    // out 32776
+    let run = run(&[19, 32776]);
+    assert!
+    (
+        matches!(run.reason, StopReason::Fault(RunFailure::OpCodeParseFailure(ReadOpCodeFailure::InvalidOperandValue)))
+    );
+}
+
+#[test]
+fn unknown_op_code_is_rejected()
+{
+    let run = run(&[22]);
+    assert!(matches!(run.reason, StopReason::Fault(RunFailure::OpCodeParseFailure(ReadOpCodeFailure::InvalidOpCode))));
+}
+
+#[test]
+fn running_past_the_end_of_memory_fails()
+{
+    let run = run(&[21]);
+    assert!(matches!(run.reason, StopReason::Fault(RunFailure::OpCodeParseFailure(ReadOpCodeFailure::NotEnoughMemory))));
+}
+
+#[test]
+fn truncated_instruction_fails()
+{
+    // add r0 1 <missing>
+    let run = run(&[9, R0, 1]);
+    assert!(matches!(run.reason, StopReason::Fault(RunFailure::OpCodeParseFailure(ReadOpCodeFailure::NotEnoughMemory))));
+}
@@ -0,0 +1,61 @@
+extern crate synacor_challenge;
+
+mod common;
+
+use common::R0;
+use synacor_challenge::code_watch::{CodeWatch, Modification};
+use synacor_challenge::jit::Backend;
+use synacor_challenge::run_until::{StopConditions, StopReason};
+use synacor_challenge::vm::VM;
+
+// 0: wmem 20 5; 3: set r0 0; 6: out 'A'; 8: jt r0 19; 11: wmem 7 'B'
+// 14: set r0 1; 17: jmp 6; 19: halt; 20: data
+const PROGRAM : [u16; 21] = [16, 20, 5, 1, R0, 0, 19, 65, 7, R0, 19, 16, 7, 66, 1, R0, 1, 6, 6, 0, 0];
+
+fn run(backend : Backend) -> VM
+{
+    let mut vm = common::vm(&PROGRAM);
+    vm.set_backend(backend);
+    vm.set_code_watch(CodeWatch::new());
+    assert!(matches!(vm.run(&StopConditions::new()), StopReason::Halted));
+    assert_eq!(vm.take_output(), "AB");
+    vm
+}
+
+#[test]
+fn writes_to_decoded_code_are_recorded()
+{
+    for &backend in &[Backend::Interpreter, Backend::BlockJit]
+    {
+        let vm = run(backend);
+        let code_watch = vm.code_watch().unwrap();
+        assert!(code_watch.is_decoded(7));
+        assert!(!code_watch.is_decoded(20));
+        assert_eq!
+        (
+            code_watch.modifications(),
+            [Modification { step : 4, program_counter : 11, address : 7, old : 65, new : 66 }]
+        );
+        assert_eq!(code_watch.report(), "1 writes to code in 1 ranges\n7..8: 1 writes, steps 4 to 4, written by 11\n");
+    }
+}
+
+#[test]
+fn modifications_are_grouped_into_ranges()
+{
+    let mut code_watch = CodeWatch::new();
+    code_watch.on_decode(10, 4);
+    code_watch.on_decode(20, 2);
+    code_watch.on_write(5, 100, 12, 1, 2);
+    code_watch.on_write(3, 101, 11, 1, 2);
+    code_watch.on_write(7, 100, 12, 2, 3);
+    code_watch.on_write(9, 102, 21, 1, 2);
+    code_watch.on_write(9, 102, 30, 1, 2);
+
+    let ranges = code_watch.modified_ranges();
+    assert_eq!(ranges.len(), 2);
+    assert_eq!((ranges[0].start, ranges[0].end, ranges[0].writes), (11, 13, 3));
+    assert_eq!((ranges[0].first_step, ranges[0].last_step), (3, 7));
+    assert_eq!(ranges[0].writers.iter().cloned().collect::<Vec<u16>>(), [100, 101]);
+    assert_eq!((ranges[1].start, ranges[1].end, ranges[1].writes), (21, 22, 1));
+}
@@ -0,0 +1,170 @@
+use std::collections::HashMap;
+use synacor_challenge::run_until::{StopConditions, StopReason};
+use synacor_challenge::vm::VM;
+use super::{R0, R1, R2, R3};
+
+// Exit target that kills the player, the program halts.
+pub const DEATH : usize = usize::MAX;
+
+pub struct Room
+{
+    pub name : &'static str,
+    pub description : &'static str,
+    pub items : &'static [&'static str],
+    pub exits : &'static [(&'static str, usize)],
+}
+
+// Assembles a tiny text adventure over `rooms` that prints rooms the way
+// the challenge does. A command is known by its first letter: `l` looks,
+// the first letter of an exit walks through it, anything else is not
+// understood. The current room is kept in memory so that it is part of
+// the memory hash. The game starts in room 0.
+pub fn assemble(rooms : &[Room]) -> Vec<u16>
+{
+    let mut asm = Assembler::default();
+
+    asm.jump("print 0");
+
+    asm.label("prompt");
+    asm.print("\nWhat do you do?\n");
+    // r1 is the first letter, the rest of the line is skipped.
+    asm.emit(&[20, R1, 1, R0, R1]);
+    asm.label("read");
+    asm.emit(&[4, R2, R0, 10]);
+    asm.emit(&[7, R2]);
+    asm.address("dispatch");
+    asm.emit(&[20, R0]);
+    asm.jump("read");
+
+    asm.label("dispatch");
+    asm.emit(&[15, R2]);
+    asm.address("room");
+    for k in 0..rooms.len()
+    {
+        asm.emit(&[4, R3, R2, k as u16, 7, R3]);
+        asm.address(&format!("dispatch {}", k));
+    }
+    asm.emit(&[0]);
+
+    for (k, room) in rooms.iter().enumerate()
+    {
+        asm.label(&format!("dispatch {}", k));
+        asm.emit(&[4, R3, R1, 'l' as u16, 7, R3]);
+        asm.address(&format!("print {}", k));
+        for (i, &(exit, _)) in room.exits.iter().enumerate()
+        {
+            asm.emit(&[4, R3, R1, exit.as_bytes()[0] as u16, 7, R3]);
+            asm.address(&format!("move {} {}", k, i));
+        }
+        asm.jump("unknown");
+
+        for (i, &(_, target)) in room.exits.iter().enumerate()
+        {
+            asm.label(&format!("move {} {}", k, i));
+            if target == DEATH
+            {
+                asm.print("You die.\n");
+                asm.emit(&[0]);
+            }
+            else
+            {
+                asm.emit(&[16]);
+                asm.address("room");
+                asm.emit(&[target as u16]);
+                asm.jump(&format!("print {}", target));
+            }
+        }
+
+        asm.label(&format!("print {}", k));
+        asm.print(&describe(room));
+        asm.jump("prompt");
+    }
+
+    asm.label("unknown");
+    asm.print("I don't understand; try 'help' for instructions.\n");
+    asm.jump("prompt");
+
+    asm.label("room");
+    asm.emit(&[0]);
+    asm.finish()
+}
+
+// The game over `rooms` waiting for its first command.
+pub fn start(rooms : &[Room]) -> VM
+{
+    let mut vm = super::vm(&assemble(rooms));
+    assert!(matches!(vm.run(&StopConditions::new()), StopReason::InputNeeded));
+    vm.take_output();
+    vm
+}
+
+fn describe(room : &Room) -> String
+{
+    let mut text = format!("== {} ==\n{}\n\n", room.name, room.description);
+    if !room.items.is_empty()
+    {
+        text.push_str("Things of interest here:\n");
+        for item in room.items
+        {
+            text.push_str(&format!("- {}\n", item));
+        }
+        text.push('\n');
+    }
+    text.push_str(&format!("There are {} exits:\n", room.exits.len()));
+    for &(exit, _) in room.exits
+    {
+        text.push_str(&format!("- {}\n", exit));
+    }
+    text
+}
+
+#[derive(Default)]
+struct Assembler
+{
+    words : Vec<u16>,
+    labels : HashMap<String, u16>,
+    // Words to fill with the address of a label once it is known.
+    fixups : Vec<(usize, String)>,
+}
+
+impl Assembler
+{
+    fn emit(&mut self, words : &[u16])
+    {
+        self.words.extend_from_slice(words);
+    }
+
+    fn label(&mut self, name : &str)
+    {
+        self.labels.insert(name.to_owned(), self.words.len() as u16);
+    }
+
+    fn address(&mut self, name : &str)
+    {
+        self.fixups.push((self.words.len(), name.to_owned()));
+        self.words.push(0);
+    }
+
+    fn jump(&mut self, name : &str)
+    {
+        self.emit(&[6]);
+        self.address(name);
+    }
+
+    fn print(&mut self, text : &str)
+    {
+        for c in text.chars()
+        {
+            self.emit(&[19, c as u16]);
+        }
+    }
+
+    fn finish(mut self) -> Vec<u16>
+    {
+        for (at, ref name) in self.fixups
+        {
+            self.words[at] = self.labels[name];
+        }
+        self.words
+    }
+}
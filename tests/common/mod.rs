@@ -0,0 +1,77 @@
+#![allow(dead_code)]
+
+pub mod adventure;
+
+use std::collections::VecDeque;
+use synacor_challenge::conformance::Conformance;
+use synacor_challenge::console::{Input, Output};
+use synacor_challenge::jit::Backend;
+use synacor_challenge::run_until::{StopConditions, StopReason};
+use synacor_challenge::vm::VM;
+
+pub const R0 : u16 = 32768;
+pub const R1 : u16 = 32769;
+pub const R2 : u16 = 32770;
+pub const R3 : u16 = 32771;
+pub const R7 : u16 = 32775;
+
+const MAX_STEPS : u64 = 1000;
+
+pub struct Run
+{
+    pub vm : VM,
+    pub reason : StopReason,
+    pub output : String,
+}
+
+// A VM over `program` reading its input from a queue and printing to an
+// in-memory buffer.
+pub fn vm(program : &[u16]) -> VM
+{
+    let mut vm = VM::new(program.to_vec());
+    vm.set_input(Input::Queue(VecDeque::new()));
+    vm.set_output(Output::Buffer(String::new()));
+    vm
+}
+
+pub fn run(program : &[u16]) -> Run
+{
+    run_with(program, Conformance::current(), &[])
+}
+
+pub fn run_with_input(program : &[u16], input : &[&str]) -> Run
+{
+    run_with(program, Conformance::current(), input)
+}
+
+// Runs `program` with both backends, its output going to an in-memory
+// buffer, and checks both runs agree before handing back the interpreter run.
+pub fn run_with(program : &[u16], conformance : Conformance, input : &[&str]) -> Run
+{
+    let mut runs = vec!();
+    for &backend in &[Backend::Interpreter, Backend::BlockJit]
+    {
+        let mut vm = vm(program);
+        vm.set_backend(backend);
+        vm.set_conformance(conformance);
+        for line in input
+        {
+            vm.push_input_line(line);
+        }
+        let reason = vm.run(&StopConditions::new().max_steps(MAX_STEPS));
+        let output = vm.take_output();
+        runs.push(Run { vm, reason, output });
+    }
+
+    let jit = runs.pop().unwrap();
+    let interpreter = runs.pop().unwrap();
+    assert_eq!(format!("{:?}", interpreter.reason), format!("{:?}", jit.reason));
+    assert_eq!(interpreter.vm.registers(), jit.vm.registers());
+    assert_eq!(interpreter.vm.stack(), jit.vm.stack());
+    assert_eq!(interpreter.vm.backtrace(), jit.vm.backtrace());
+    assert_eq!(interpreter.vm.program_counter(), jit.vm.program_counter());
+    assert_eq!(interpreter.vm.step_nb(), jit.vm.step_nb());
+    assert!(interpreter.vm.memory() == jit.vm.memory());
+    assert_eq!(interpreter.output, jit.output);
+    interpreter
+}
@@ -0,0 +1,214 @@
+extern crate synacor_challenge;
+
+mod common;
+
+use common::{R0, R1, R2};
+use synacor_challenge::conformance::Conformance;
+use synacor_challenge::opcode::{OpCode, ReadOpCodeFailure};
+use synacor_challenge::run_until::{StopConditions, StopReason};
+use synacor_challenge::vm::{VM, RunFailure};
+
+fn run(program : &[u16], conformance : Conformance) -> (VM, StopReason)
+{
+    let run = common::run_with(program, conformance, &[]);
+    (run.vm, run.reason)
+}
+
+// ret with an empty stack
+const EMPTY_RETURN : [u16; 1] = [18];
+
+#[test]
+fn current_fails_on_return_with_empty_stack()
+{
+    let (_, reason) = run(&EMPTY_RETURN, Conformance::current());
+    assert!(matches!(reason, StopReason::Fault(RunFailure::CannotReturnStackIsEmpty)));
+}
+
+#[test]
+fn strict_halts_on_return_with_empty_stack()
+{
+    let (_, reason) = run(&EMPTY_RETURN, Conformance::strict());
+    assert!(matches!(reason, StopReason::Halted));
+}
+
+// set r1 7; rmem r0 11; add r2 r0 0; halt; 11: a register reference
+const REGISTER_REFERENCE : [u16; 12] = [1, R1, 7, 15, R0, 11, 9, R2, R0, 0, 0, R1];
+
+#[test]
+fn current_refuses_to_load_a_register_reference()
+{
+    let (vm, reason) = run(&REGISTER_REFERENCE, Conformance::current());
+    match reason
+    {
+        StopReason::Fault(RunFailure::InvalidLiteral { program_counter, value, .. }) =>
+        {
+            assert_eq!(program_counter, 3);
+            assert_eq!(value, R1);
+        },
+        reason => panic!("unexpected stop {:?}", reason),
+    }
+    assert_eq!(vm.registers()[0], 0);
+}
+
+#[test]
+fn strict_reads_registers_once()
+{
+    let (vm, reason) = run(&REGISTER_REFERENCE, Conformance::strict());
+    assert!(matches!(reason, StopReason::Halted));
+    assert_eq!(vm.registers()[0], R1);
+    // 32769 + 0 modulo 32768, not the 7 held by r1
+    assert_eq!(vm.registers()[2], 1);
+}
+
+// rmem r0 20000; wmem 25000 42; rmem r1 25000; halt
+const FAR_ADDRESSES : [u16; 10] = [15, R0, 20000, 16, 25000, 42, 15, R1, 25000, 0];
+
+#[test]
+fn current_fails_on_read_outside_of_the_image()
+{
+    let (_, reason) = run(&FAR_ADDRESSES, Conformance::current());
+    match reason
+    {
+        StopReason::Fault(RunFailure::InvalidAddress { program_counter, address, .. }) =>
+        {
+            assert_eq!(program_counter, 0);
+            assert_eq!(address, 20000);
+        },
+        reason => panic!("unexpected stop {:?}", reason),
+    }
+}
+
+#[test]
+fn current_fails_on_write_outside_of_the_image()
+{
+    let (_, reason) = run(&FAR_ADDRESSES[3..], Conformance::current());
+    match reason
+    {
+        StopReason::Fault(RunFailure::InvalidAddress { program_counter, address, .. }) =>
+        {
+            assert_eq!(program_counter, 0);
+            assert_eq!(address, 25000);
+        },
+        reason => panic!("unexpected stop {:?}", reason),
+    }
+}
+
+#[test]
+fn strict_addresses_the_whole_address_space()
+{
+    let (vm, reason) = run(&FAR_ADDRESSES, Conformance::strict());
+    assert!(matches!(reason, StopReason::Halted));
+    assert_eq!(vm.memory().len(), 32768);
+    assert_eq!(vm.registers()[0], 0);
+    assert_eq!(vm.registers()[1], 42);
+    assert_eq!(vm.memory()[25000], 42);
+}
+
+// set r0 8; jmp r0; set r1 9; halt
+const REGISTER_JUMP : [u16; 9] = [1, R0, 8, 6, R0, 1, R1, 9, 0];
+
+#[test]
+fn current_rejects_register_jump_targets()
+{
+    let (vm, reason) = run(&REGISTER_JUMP, Conformance::current());
+    assert!
+    (
+        matches!(reason, StopReason::Fault(RunFailure::OpCodeParseFailure(ReadOpCodeFailure::InvalidOperandValue)))
+    );
+    assert_eq!(vm.program_counter(), 3);
+}
+
+#[test]
+fn strict_jumps_to_registers()
+{
+    let (vm, reason) = run(&REGISTER_JUMP, Conformance::strict());
+    assert!(matches!(reason, StopReason::Halted));
+    assert_eq!(vm.registers()[1], 0);
+    assert_eq!(vm.program_counter(), 8);
+}
+
+// out r0; halt
+const OUT_R0 : [u16; 3] = [19, R0, 0];
+
+#[test]
+fn current_refuses_to_set_a_register_to_a_register_reference()
+{
+    let mut vm = common::vm(&OUT_R0);
+    assert!(matches!(vm.set_register(0, R0), Err(RunFailure::InvalidRegisterValue { register : 0, value : R0 })));
+    assert_eq!(vm.registers()[0], 0);
+}
+
+#[test]
+fn current_stops_following_registers_that_loop()
+{
+    let mut vm = common::vm(&OUT_R0);
+    vm.set_conformance(Conformance::strict());
+    vm.set_register(0, R1).unwrap();
+    vm.set_register(1, R0).unwrap();
+    vm.set_conformance(Conformance::current());
+    match vm.run(&StopConditions::new())
+    {
+        StopReason::Fault(RunFailure::RegisterLoop { program_counter, register, .. }) =>
+        {
+            assert_eq!(program_counter, 0);
+            assert_eq!(register, 0);
+        },
+        reason => panic!("unexpected stop {:?}", reason),
+    }
+}
+
+#[test]
+fn current_reports_the_register_value_it_cannot_use()
+{
+    let mut vm = common::vm(&OUT_R0);
+    vm.set_conformance(Conformance::strict());
+    vm.set_register(0, 32776).unwrap();
+    vm.set_conformance(Conformance::current());
+    match vm.run(&StopConditions::new())
+    {
+        StopReason::Fault(RunFailure::InvalidValue { program_counter, op_code : OpCode::Out(_), value }) =>
+        {
+            assert_eq!(program_counter, 0);
+            assert_eq!(value, 32776);
+        },
+        reason => panic!("unexpected stop {:?}", reason),
+    }
+}
+
+// push 5; pop 7; halt
+const POP_TO_A_NUMBER : [u16; 5] = [2, 5, 3, 7, 0];
+
+#[test]
+fn failed_pop_leaves_the_stack_alone()
+{
+    let (vm, reason) = run(&POP_TO_A_NUMBER, Conformance::current());
+    match reason
+    {
+        StopReason::Fault(RunFailure::InvalidValue { program_counter, op_code : OpCode::Pop(_), value }) =>
+        {
+            assert_eq!(program_counter, 2);
+            assert_eq!(value, 7);
+        },
+        reason => panic!("unexpected stop {:?}", reason),
+    }
+    assert_eq!(vm.stack(), &[5]);
+}
+
+// rmem r0 6; push r0; ret; 6: a register reference
+const RETURN_TO_A_REGISTER : [u16; 7] = [15, R0, 6, 2, R0, 18, R1];
+
+#[test]
+fn failed_return_leaves_the_stack_alone()
+{
+    let (vm, reason) = run(&RETURN_TO_A_REGISTER, Conformance::strict());
+    match reason
+    {
+        StopReason::Fault(RunFailure::InvalidLiteral { program_counter, op_code : OpCode::Return, value }) =>
+        {
+            assert_eq!(program_counter, 5);
+            assert_eq!(value, R1);
+        },
+        reason => panic!("unexpected stop {:?}", reason),
+    }
+    assert_eq!(vm.stack(), &[R1]);
+}
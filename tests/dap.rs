@@ -0,0 +1,272 @@
+extern crate synacor_challenge;
+extern crate serde_json;
+
+mod common;
+
+use std::env;
+use std::fs;
+use std::io::{Cursor, ErrorKind, Write};
+use std::process::{Command, Stdio};
+use serde_json::{Value, json};
+use synacor_challenge::dap::{DapServer, read_message, write_message};
+use common::{R0, R1};
+
+// 0: push 7; 2: call 7; 4: out 'B'; 6: halt
+// 7: push 9; 9: pop r1; 11: out 'A'; 13: ret
+const CALL_PROGRAM : [u16; 14] = [2, 7, 17, 7, 19, 66, 0, 2, 9, 3, R1, 19, 65, 18];
+
+// 0: in r0; 2: out r0; 4: jmp 0
+const ECHO_PROGRAM : [u16; 6] = [20, R0, 19, R0, 6, 0];
+
+fn write_file(name : &str, content : &[u8]) -> String
+{
+    let mut path = env::temp_dir();
+    path.push(format!("synacor_dap_{}_{}", std::process::id(), name));
+    fs::write(&path, content).unwrap();
+    path.to_str().unwrap().to_owned()
+}
+
+fn image(program : &[u16]) -> Vec<u8>
+{
+    program.iter().flat_map(|word| word.to_le_bytes().to_vec()).collect()
+}
+
+fn requests(requests : &[Value]) -> Vec<u8>
+{
+    let mut input = vec!();
+    for (seq, request) in requests.iter().enumerate()
+    {
+        let mut request = request.clone();
+        request["seq"] = json!(seq + 1);
+        request["type"] = json!("request");
+        write_message(&mut input, &request).unwrap();
+    }
+    input
+}
+
+fn read_messages(output : Vec<u8>) -> Vec<Value>
+{
+    let mut output = Cursor::new(output);
+    let mut messages = vec!();
+    while let Some(message) = read_message(&mut output).unwrap()
+    {
+        messages.push(message);
+    }
+    messages
+}
+
+// Sends `requests` in one go and returns every message sent back.
+fn session(requests : &[Value]) -> Vec<Value>
+{
+    let mut output = vec!();
+    DapServer::new().serve(Cursor::new(self::requests(requests)), &mut output).unwrap();
+    read_messages(output)
+}
+
+fn response<'a>(messages : &'a [Value], command : &str, nth : usize) -> &'a Value
+{
+    messages
+        .iter()
+        .filter(|message| message["type"] == "response" && message["command"] == command)
+        .nth(nth)
+        .unwrap()
+}
+
+fn events<'a>(messages : &'a [Value], name : &str) -> Vec<&'a Value>
+{
+    messages.iter().filter(|message| message["type"] == "event" && message["event"] == name).collect()
+}
+
+#[test]
+fn breakpoints_by_label_and_step_out()
+{
+    let program = write_file("call.bin", &image(&CALL_PROGRAM));
+    let symbols = write_file("call.sym", b"# label address\nmain 0\nsub 0x7\n9 body\n");
+    let messages =
+        session
+        (
+            &[
+                json!({ "command" : "initialize", "arguments" : {} }),
+                json!({ "command" : "launch", "arguments" : { "program" : program, "symbols" : symbols, "stopOnEntry" : true } }),
+                json!({ "command" : "setFunctionBreakpoints", "arguments" : { "breakpoints" : [{ "name" : "body" }, { "name" : "nowhere" }] } }),
+                json!({ "command" : "configurationDone" }),
+                json!({ "command" : "continue", "arguments" : { "threadId" : 1 } }),
+                json!({ "command" : "stackTrace", "arguments" : { "threadId" : 1 } }),
+                json!({ "command" : "scopes", "arguments" : { "frameId" : 1 } }),
+                json!({ "command" : "variables", "arguments" : { "variablesReference" : 2 } }),
+                json!({ "command" : "variables", "arguments" : { "variablesReference" : 3 } }),
+                json!({ "command" : "stepOut", "arguments" : { "threadId" : 1 } }),
+                json!({ "command" : "variables", "arguments" : { "variablesReference" : 1 } }),
+                json!({ "command" : "continue", "arguments" : { "threadId" : 1 } }),
+                json!({ "command" : "disconnect" }),
+            ]
+        );
+
+    assert!(messages.iter().all(|message| message["type"] != "response" || message["success"] == true));
+    let breakpoints = &response(&messages, "setFunctionBreakpoints", 0)["body"]["breakpoints"];
+    assert_eq!(breakpoints[0]["verified"], true);
+    assert_eq!(breakpoints[1]["verified"], false);
+
+    let stopped : Vec<&Value> = events(&messages, "stopped").iter().map(|event| &event["body"]["reason"]).collect();
+    assert_eq!(stopped, [&json!("entry"), &json!("breakpoint"), &json!("step")]);
+
+    let frames = &response(&messages, "stackTrace", 0)["body"]["stackFrames"];
+    assert_eq!(frames.as_array().unwrap().len(), 2);
+    assert_eq!(frames[0]["name"], "body");
+    assert_eq!(frames[1]["name"], "main+2");
+    assert_eq!(frames[1]["instructionPointerReference"], "0x0002");
+    let scopes = &response(&messages, "scopes", 0)["body"]["scopes"];
+    assert_eq!(scopes[1]["variablesReference"], 3);
+
+    // The return address between the two frames is not shown as data.
+    let callee_data = &response(&messages, "variables", 0)["body"]["variables"];
+    assert_eq!(callee_data.as_array().unwrap().len(), 1);
+    assert_eq!(callee_data[0]["name"], "[2]");
+    assert_eq!(callee_data[0]["value"], "9 (0x0009)");
+    let caller_data = &response(&messages, "variables", 1)["body"]["variables"];
+    assert_eq!(caller_data.as_array().unwrap().len(), 1);
+    assert_eq!(caller_data[0]["name"], "[0]");
+    assert_eq!(caller_data[0]["value"], "7 (0x0007)");
+
+    let registers = &response(&messages, "variables", 2)["body"]["variables"];
+    assert_eq!(registers[1]["value"], "9 (0x0009)");
+    assert_eq!(registers[8]["name"], "pc");
+    assert_eq!(registers[8]["value"], "4 (0x0004)");
+
+    let output : String = events(&messages, "output").iter().map(|event| event["body"]["output"].as_str().unwrap()).collect();
+    assert_eq!(output, "AB");
+    assert_eq!(events(&messages, "terminated").len(), 1);
+}
+
+#[test]
+fn input_script_then_console_input()
+{
+    let program = write_file("echo.bin", &image(&ECHO_PROGRAM));
+    let script = write_file("echo.txt", b"hi\n");
+    let messages =
+        session
+        (
+            &[
+                json!({ "command" : "initialize", "arguments" : {} }),
+                json!({ "command" : "launch", "arguments" : { "program" : program, "input" : script } }),
+                json!({ "command" : "setInstructionBreakpoints", "arguments" : { "breakpoints" : [] } }),
+                json!({ "command" : "configurationDone" }),
+                json!({ "command" : "evaluate", "arguments" : { "expression" : "yo", "context" : "repl" } }),
+                json!({ "command" : "continue", "arguments" : { "threadId" : 1 } }),
+                json!({ "command" : "disconnect" }),
+            ]
+        );
+
+    let stopped = events(&messages, "stopped");
+    assert_eq!(stopped.len(), 2);
+    assert!(stopped.iter().all(|event| event["body"]["reason"] == "pause"));
+    let output : Vec<&str> = events(&messages, "output").iter().map(|event| event["body"]["output"].as_str().unwrap()).collect();
+    assert_eq!(output, ["hi\n", "yo\n"]);
+}
+
+#[test]
+fn launch_failure_is_reported()
+{
+    let messages =
+        session
+        (
+            &[
+                json!({ "command" : "launch", "arguments" : { "program" : "/nonexistent/challenge.bin" } }),
+                json!({ "command" : "stackTrace", "arguments" : { "threadId" : 1 } }),
+            ]
+        );
+
+    assert_eq!(response(&messages, "launch", 0)["success"], false);
+    assert_eq!(response(&messages, "stackTrace", 0)["success"], false);
+}
+
+#[test]
+fn tool_commands_answer_in_output_events()
+{
+    let program = write_file("tool.bin", &image(&ECHO_PROGRAM));
+    let messages =
+        session
+        (
+            &[
+                json!({ "command" : "launch", "arguments" : { "program" : program } }),
+                json!({ "command" : "configurationDone" }),
+                json!({ "command" : "evaluate", "arguments" : { "expression" : "smc", "context" : "repl" } }),
+                json!({ "command" : "continue", "arguments" : { "threadId" : 1 } }),
+                json!({ "command" : "disconnect" }),
+            ]
+        );
+
+    let output : Vec<&str> = events(&messages, "output").iter().map(|event| event["body"]["output"].as_str().unwrap()).collect();
+    assert_eq!(output, ["code watch is off\n"]);
+}
+
+// Diagnostics from the decoder must not end up between the messages on
+// stdout.
+#[test]
+fn invalid_op_code_keeps_stdout_framed()
+{
+    // 0: out 'A'; 2: an op code that does not exist
+    let program = write_file("invalid.bin", &image(&[19, 65, 22]));
+    let input =
+        requests
+        (
+            &[
+                json!({ "command" : "initialize", "arguments" : {} }),
+                json!({ "command" : "launch", "arguments" : { "program" : program } }),
+                json!({ "command" : "configurationDone" }),
+                json!({ "command" : "disconnect" }),
+            ]
+        );
+    let mut child =
+        Command::new(env!("CARGO_BIN_EXE_synacor_challenge"))
+            .arg("--dap")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+    child.stdin.take().unwrap().write_all(&input).unwrap();
+    let result = child.wait_with_output().unwrap();
+
+    assert!(!String::from_utf8_lossy(&result.stdout).contains("Invalid OpCode for value"));
+    let messages = read_messages(result.stdout);
+    assert_eq!(messages.last().unwrap()["command"], "disconnect");
+    let stopped = events(&messages, "stopped");
+    assert_eq!(stopped.len(), 1);
+    assert_eq!(stopped[0]["body"]["reason"], "exception");
+    assert!(String::from_utf8_lossy(&result.stderr).contains("Invalid OpCode for value 22"));
+}
+
+#[test]
+fn pause_stops_a_program_that_loops()
+{
+    // 0: jmp 0
+    let program = write_file("loop.bin", &image(&[6, 0]));
+    let messages =
+        session
+        (
+            &[
+                json!({ "command" : "initialize", "arguments" : {} }),
+                json!({ "command" : "launch", "arguments" : { "program" : program } }),
+                json!({ "command" : "configurationDone" }),
+                json!({ "command" : "pause", "arguments" : { "threadId" : 1 } }),
+                json!({ "command" : "stackTrace", "arguments" : { "threadId" : 1 } }),
+                json!({ "command" : "disconnect" }),
+            ]
+        );
+
+    let stopped = events(&messages, "stopped");
+    assert_eq!(stopped.len(), 1);
+    assert_eq!(stopped[0]["body"]["reason"], "pause");
+    assert_eq!(response(&messages, "pause", 0)["success"], true);
+    assert_eq!(response(&messages, "stackTrace", 0)["body"]["stackFrames"][0]["instructionPointerReference"], "0x0000");
+    assert_eq!(messages.last().unwrap()["command"], "disconnect");
+}
+
+#[test]
+fn oversized_messages_are_refused()
+{
+    let mut input = Cursor::new(b"Content-Length: 18446744073709551615\r\n\r\n{}".to_vec());
+    let error = read_message(&mut input).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidData);
+}
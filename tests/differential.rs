@@ -0,0 +1,102 @@
+extern crate synacor_challenge;
+
+use std::fs;
+use synacor_challenge::conformance::Conformance;
+use synacor_challenge::jit::Backend;
+use synacor_challenge::loader::{read_challenge_file, convert_to_u16_le};
+use synacor_challenge::reference::{lockstep, vm_for, ReferenceVm, LockstepEnd, StepEnd};
+
+const INPUT : [&str; 2] = ["north", "take 42"];
+
+// xorshift64, enough to spread programs over every op code and operand kind.
+struct Random(u64);
+
+impl Random
+{
+    fn next(&mut self) -> u64
+    {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    // Mostly op codes, registers and small addresses so that programs run
+    // for a while instead of faulting on their first word.
+    fn word(&mut self) -> u16
+    {
+        let r = self.next();
+        let high = (r >> 8) as u16;
+        match r % 8
+        {
+            0 | 1 => high % 22,
+            2 | 3 => 32768 + high % 8,
+            4 | 5 => high % 64,
+            6 => 32767 - high % 4,
+            _ => high,
+        }
+    }
+}
+
+#[test]
+fn random_programs_match_the_reference()
+{
+    let mut random = Random(0x5eed_1234_abcd_0001);
+    for i in 0..3000
+    {
+        let length = 8 + (random.next() % 120) as usize;
+        let program : Vec<u16> = (0..length).map(|_| random.word()).collect();
+
+        let mut vm = vm_for(&program);
+        if i % 2 == 1
+        {
+            vm.set_backend(Backend::BlockJit);
+        }
+        let mut reference = ReferenceVm::new(&program);
+        for line in &INPUT
+        {
+            vm.push_input_line(line);
+            reference.push_input_line(line);
+        }
+
+        if let Err(divergence) = lockstep(&mut vm, &mut reference, 5000)
+        {
+            panic!("program {} {:?} diverged: {:?}", i, program, divergence);
+        }
+    }
+}
+
+#[test]
+fn walkthrough_matches_the_reference()
+{
+    let content = read_challenge_file("challenge.bin").unwrap();
+    let program = convert_to_u16_le(&content).ok().unwrap();
+
+    let mut vm = vm_for(&program);
+    let mut reference = ReferenceVm::new(&program);
+    for line in fs::read_to_string("data/solution.txt").unwrap().lines()
+    {
+        vm.push_input_line(line);
+        reference.push_input_line(line);
+    }
+
+    match lockstep(&mut vm, &mut reference, 10_000_000)
+    {
+        Ok(end) => assert_eq!(end, LockstepEnd::Stopped(StepEnd::InputNeeded)),
+        Err(divergence) => panic!("walkthrough diverged: {:?}", divergence),
+    }
+}
+
+#[test]
+fn lockstep_reports_the_first_divergence()
+{
+    // set r0 8; jmp r0; halt -- the current profile refuses register jump targets
+    let program = [1, 32768, 8, 6, 32768, 0, 0, 0, 0];
+    let mut vm = vm_for(&program);
+    vm.set_conformance(Conformance::current());
+    let mut reference = ReferenceVm::new(&program);
+
+    let divergence = lockstep(&mut vm, &mut reference, 100).unwrap_err();
+    assert_eq!(divergence.step, 1);
+    assert_eq!(divergence.program_counter, 3);
+}
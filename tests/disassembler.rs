@@ -0,0 +1,25 @@
+extern crate synacor_challenge;
+
+mod common;
+
+use common::{R0, R1};
+use synacor_challenge::disassembler::{disassemble, listing, Line};
+
+#[test]
+fn instructions_and_operands()
+{
+    // set r0 5; add r1 r0 1; out 'A'; out 10; halt
+    let program = [1, R0, 5, 9, R1, R0, 1, 19, 65, 19, 10, 0];
+    let text : Vec<String> = listing(&program[..], 0, 5).into_iter().map(|line| line.text).collect();
+    assert_eq!(text, ["set r0 5", "add r1 r0 1", "out 'A'", "out '\\n'", "halt"]);
+}
+
+#[test]
+fn data_and_truncated_instructions()
+{
+    let program = [22, 40000, 9, R0];
+    assert_eq!(disassemble(&program[..], 0), Line { address : 0, size : 1, text : ".word 22".to_owned() });
+    assert_eq!(disassemble(&program[..], 1), Line { address : 1, size : 1, text : ".word 40000".to_owned() });
+    assert_eq!(disassemble(&program[..], 2), Line { address : 2, size : 4, text : "add r0 ? ?".to_owned() });
+    assert_eq!(disassemble(&program[..], 4).text, "?");
+}
@@ -0,0 +1,63 @@
+extern crate synacor_challenge;
+
+mod common;
+
+use std::env;
+use std::fs;
+use common::adventure::{start, Room, DEATH};
+use synacor_challenge::explorer::Explorer;
+use synacor_challenge::harvester::CodeHarvester;
+
+// Hall <-> Library, the library shows a code.
+const CODE_ROOMS : [Room; 2] =
+    [
+        Room { name : "Hall", description : "A bare hall.", items : &[], exits : &[("north", 1)] },
+        Room { name : "Library", description : "A wall reads: AbCdEfGhIjKl", items : &[], exits : &[("south", 0)] },
+    ];
+
+// Cave -> Maze <-> Maze, two rooms that only differ by the memory state.
+// The second maze room has a way down to a fatal fall.
+const MAZE : [Room; 3] =
+    [
+        Room { name : "Cave", description : "A damp cave.", items : &[], exits : &[("north", 1)] },
+        Room { name : "Maze", description : "Twisty passages.", items : &[], exits : &[("east", 2), ("south", 0)] },
+        Room { name : "Maze", description : "Twisty passages.", items : &["lamp"], exits : &[("west", 1), ("down", DEATH)] },
+    ];
+
+#[test]
+fn rooms_are_told_apart_by_memory_and_visited_once()
+{
+    let vm = start(&MAZE);
+    let graph = Explorer::new().explore(&vm).unwrap();
+
+    let names : Vec<&str> = graph.rooms.iter().map(|room| room.name.as_str()).collect();
+    assert_eq!(names, ["Cave", "Maze", "Maze"]);
+    assert_ne!(graph.rooms[1].fingerprint, graph.rooms[2].fingerprint);
+    assert_eq!(graph.rooms[2].items, ["lamp"]);
+    assert_eq!(graph.rooms[2].exits, ["west", "down"]);
+    let passages : Vec<(usize, &str, usize)> =
+        graph.passages.iter().map(|p| (p.from, p.direction.as_str(), p.to)).collect();
+    assert_eq!(passages, [(0, "north", 1), (1, "east", 2), (1, "south", 0), (2, "west", 1)]);
+    assert_eq!(graph.fatal_exits, [(2, "down".to_owned())]);
+}
+
+#[test]
+fn exploring_leaves_the_code_file_alone()
+{
+    let mut path = env::temp_dir();
+    path.push(format!("synacor_explorer_codes_{}.tsv", std::process::id()));
+    let file_name = path.to_str().unwrap().to_owned();
+    fs::write(&file_name, "").unwrap();
+
+    let mut vm = start(&CODE_ROOMS);
+    vm.set_code_harvester(CodeHarvester::new(&file_name));
+    assert!(vm.fork().code_harvester().is_none());
+
+    let graph = Explorer::new().explore(&vm).unwrap();
+    let codes = fs::read_to_string(&file_name).unwrap();
+    fs::remove_file(&file_name).unwrap();
+
+    assert_eq!(graph.rooms.len(), 2);
+    assert_eq!(codes, "");
+    assert!(vm.code_harvester().unwrap().harvested().is_empty());
+}
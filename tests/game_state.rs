@@ -0,0 +1,32 @@
+extern crate synacor_challenge;
+
+mod common;
+
+use std::fs;
+use synacor_challenge::loader::{read_challenge_file, convert_to_u16_le};
+use synacor_challenge::run_until::{StopConditions, StopReason};
+
+const SOLUTION : &str = "data/solution.txt";
+
+// The walkthrough up to picking up the lantern in the moss cavern.
+const COMMANDS : usize = 10;
+
+#[test]
+fn tables_are_found_in_the_challenge()
+{
+    let content = read_challenge_file("challenge.bin").unwrap();
+    let mut vm = common::vm(&convert_to_u16_le(&content).ok().unwrap());
+    for line in fs::read_to_string(SOLUTION).unwrap().lines().take(COMMANDS)
+    {
+        vm.push_input_line(line);
+    }
+    assert!(matches!(vm.run(&StopConditions::new().max_steps(10_000_000)), StopReason::InputNeeded));
+    assert_eq!(vm.step_nb(), 732585);
+
+    let layout = vm.game_layout().unwrap();
+    assert_eq!(layout.item_table, 2668);
+    assert_eq!(layout.item_count, 16);
+    assert_eq!(vm.current_room().unwrap().name, "Moss cavern");
+    let inventory : Vec<String> = vm.inventory().into_iter().map(|item| item.name).collect();
+    assert_eq!(inventory, ["tablet", "empty lantern"]);
+}
@@ -0,0 +1,166 @@
+extern crate synacor_challenge;
+
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+use std::time::Duration;
+use synacor_challenge::gdb::{GdbStub, checksum};
+use synacor_challenge::vm::VM;
+
+const R0 : u16 = 32768;
+const R1 : u16 = 32769;
+
+// 0: set r0 5; 3: add r1 r0 1; 7: wmem 20 r1; 10: halt; 20: data
+fn program() -> Vec<u16>
+{
+    let mut program = vec!(1, R0, 5, 9, R1, R0, 1, 16, 20, R1, 0);
+    program.resize(21, 0);
+    program
+}
+
+struct Client
+{
+    stream : TcpStream,
+}
+
+impl Client
+{
+    // Sends a packet and returns the reply, acknowledging it like gdb does.
+    fn exchange(&mut self, packet : &str) -> String
+    {
+        self.send(packet);
+        self.reply()
+    }
+
+    fn send(&mut self, packet : &str)
+    {
+        write!(self.stream, "${}#{:02x}", packet, checksum(packet.as_bytes())).unwrap();
+        assert_eq!(self.read_byte(), b'+');
+    }
+
+    fn reply(&mut self) -> String
+    {
+        assert_eq!(self.read_byte(), b'$');
+        let mut reply = vec!();
+        loop
+        {
+            match self.read_byte()
+            {
+                b'#' => break,
+                byte => reply.push(byte),
+            }
+        }
+        let sum = [self.read_byte(), self.read_byte()];
+        assert_eq!(u8::from_str_radix(std::str::from_utf8(&sum).unwrap(), 16).unwrap(), checksum(&reply));
+        self.stream.write_all(b"+").unwrap();
+        String::from_utf8(reply).unwrap()
+    }
+
+    fn read_byte(&mut self) -> u8
+    {
+        let mut byte = [0];
+        self.stream.read_exact(&mut byte).unwrap();
+        byte[0]
+    }
+}
+
+// Serves `program` on a local port, the VM is handed back once the
+// session is over.
+fn connect(program : Vec<u16>) -> (Client, thread::JoinHandle<VM>)
+{
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    let server = thread::spawn(move ||
+    {
+        let (stream, _) = listener.accept().unwrap();
+        stream.set_nodelay(true).unwrap();
+        let mut stub = GdbStub::new(VM::new(program));
+        stub.serve(stream.try_clone().unwrap(), stream).unwrap();
+        stub.into_vm()
+    });
+
+    let gdb = Client { stream : TcpStream::connect(address).unwrap() };
+    gdb.stream.set_nodelay(true).unwrap();
+    (gdb, server)
+}
+
+#[test]
+fn scripted_session()
+{
+    let (mut gdb, server) = connect(program());
+    assert!(gdb.exchange("qSupported:multiprocess+").contains("qXfer:features:read+"));
+    assert!(gdb.exchange("qXfer:features:read:target.xml:0,fff").starts_with("l<?xml"));
+    assert_eq!(gdb.exchange("?"), "S05");
+    assert_eq!(gdb.exchange("g"), "0000".repeat(9));
+
+    // single step over `set r0 5`, the program counter is a byte address
+    assert_eq!(gdb.exchange("s"), "S05");
+    assert_eq!(gdb.exchange("p0"), "0500");
+    assert_eq!(gdb.exchange("p8"), "0600");
+
+    // break on `wmem` at word 7
+    assert_eq!(gdb.exchange("Z0,e,2"), "OK");
+    assert_eq!(gdb.exchange("c"), "S05");
+    assert_eq!(gdb.exchange("p8"), "0e00");
+    assert_eq!(gdb.exchange("p1"), "0600");
+
+    // memory is little endian words, word 20 is at byte 0x28
+    assert_eq!(gdb.exchange("m28,2"), "0000");
+    assert_eq!(gdb.exchange("M28,2:3412"), "OK");
+    assert_eq!(gdb.exchange("m28,2"), "3412");
+    assert_eq!(gdb.exchange("m0,6"), "010000800500");
+    assert_eq!(gdb.exchange("m10000,2"), "E01");
+
+    assert_eq!(gdb.exchange("P0=0900"), "OK");
+    assert_eq!(gdb.exchange("z0,e,2"), "OK");
+    assert_eq!(gdb.exchange("c"), "W00");
+    assert_eq!(gdb.exchange("D"), "OK");
+
+    let vm = server.join().unwrap();
+    assert_eq!(vm.registers()[0], 9);
+    assert_eq!(vm.memory()[20], 6);
+}
+
+#[test]
+fn register_references_are_rejected()
+{
+    // out r0; halt
+    let (mut gdb, server) = connect(vec!(19, R0, 0));
+    assert_eq!(gdb.exchange("P0=0080"), "E01");
+    assert_eq!(gdb.exchange("G4100000000000000000000000000000000000080"), "E01");
+    assert_eq!(gdb.exchange("g"), "0000".repeat(9));
+    assert_eq!(gdb.exchange("P0=4100"), "OK");
+    assert_eq!(gdb.exchange("s"), "S05");
+    assert_eq!(gdb.exchange("c"), "W00");
+    assert_eq!(gdb.exchange("D"), "OK");
+
+    let vm = server.join().unwrap();
+    assert_eq!(vm.registers()[0], 65);
+}
+
+#[test]
+fn ranges_past_the_address_space_are_rejected()
+{
+    let (mut gdb, server) = connect(program());
+    assert_eq!(gdb.exchange("mffffffffffffffff,2"), "E01");
+    assert_eq!(gdb.exchange("Mffffffffffffffff,2:0000"), "E01");
+    assert_eq!(gdb.exchange("qXfer:features:read:target.xml:10,ffffffffffffffff"), "E01");
+    assert_eq!(gdb.exchange("D"), "OK");
+    server.join().unwrap();
+}
+
+#[test]
+fn interrupt_stops_a_running_program()
+{
+    // 0: jmp 0
+    let (mut gdb, server) = connect(vec!(6, 0));
+    gdb.send("c");
+    thread::sleep(Duration::from_millis(50));
+    gdb.stream.write_all(&[0x03]).unwrap();
+    assert_eq!(gdb.reply(), "S02");
+    assert_eq!(gdb.exchange("p8"), "0000");
+    assert_eq!(gdb.exchange("D"), "OK");
+
+    let vm = server.join().unwrap();
+    assert!(vm.step_nb() > 0);
+}
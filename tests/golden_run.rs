@@ -0,0 +1,123 @@
+extern crate synacor_challenge;
+
+mod common;
+
+use std::fs;
+use synacor_challenge::jit::Backend;
+use synacor_challenge::loader::{read_challenge_file, convert_to_u16_le};
+use synacor_challenge::memory::Memory;
+use synacor_challenge::run_until::{StopConditions, StopReason};
+use synacor_challenge::vm::VM;
+
+const SOLUTION : &str = "data/solution.txt";
+const CODES : &str = "data/codeSoFar.txt";
+const GOLDEN : &str = "data/golden_run.txt";
+
+const MAX_STEPS : u64 = 100_000_000;
+
+// The readme code is printed in arch-spec, not by the program.
+const CODES_OUTSIDE_OF_THE_GAME : [&str; 1] = ["In the readme"];
+
+struct Replay
+{
+    vm : VM,
+    output : String,
+}
+
+fn replay(backend : Backend) -> Replay
+{
+    let content = read_challenge_file("challenge.bin").unwrap();
+    let memory = convert_to_u16_le(&content).ok().unwrap();
+    let mut vm = common::vm(&memory);
+    vm.set_backend(backend);
+    for line in fs::read_to_string(SOLUTION).unwrap().lines()
+    {
+        vm.push_input_line(line);
+    }
+
+    let reason = vm.run(&StopConditions::new().max_steps(MAX_STEPS));
+    assert!(matches!(reason, StopReason::InputNeeded), "walkthrough stopped with {:?}", reason);
+    let output = vm.take_output();
+    Replay { vm, output }
+}
+
+#[test]
+fn walkthrough_prints_every_code()
+{
+    let replay = replay(Backend::Interpreter);
+    assert!(!replay.output.contains("I don't understand"), "the walkthrough typed an unknown command");
+
+    let codes = fs::read_to_string(CODES).unwrap();
+    for line in codes.lines().filter(|line| !line.trim().is_empty())
+    {
+        let (place, code) = line.rsplit_once(':').unwrap();
+        if CODES_OUTSIDE_OF_THE_GAME.contains(&place)
+        {
+            continue;
+        }
+        assert!(replay.output.contains(code.trim()), "code {} ({}) was not printed", code.trim(), place);
+    }
+}
+
+fn summary(vm : &VM) -> String
+{
+    format!("steps {}\nstate_hash {:016x}\n", vm.step_nb(), vm.state_hash())
+}
+
+// Set UPDATE_GOLDEN=1 to store the current result after an intended change.
+#[test]
+fn walkthrough_matches_golden_run()
+{
+    let actual = summary(&replay(Backend::Interpreter).vm);
+    let jit = summary(&replay(Backend::BlockJit).vm);
+    assert_eq!(actual, jit, "the block backend disagrees with the interpreter");
+
+    if std::env::var("UPDATE_GOLDEN").is_ok()
+    {
+        fs::write(GOLDEN, &actual).unwrap();
+    }
+    let golden = fs::read_to_string(GOLDEN).unwrap();
+    assert_eq!(actual, golden);
+}
+
+// Builds a VM from scratch holding the memory, registers, stack and program
+// counter of `vm`.
+fn rebuild(vm : &VM) -> VM
+{
+    let mut fresh = VM::new(vm.memory().to_vec());
+    fresh.set_conformance(vm.conformance());
+    for (r, &value) in vm.registers().iter().enumerate()
+    {
+        fresh.set_register(r, value).unwrap();
+    }
+    for &value in vm.stack()
+    {
+        fresh.push_stack(value);
+    }
+    fresh.set_program_counter(vm.program_counter());
+    fresh
+}
+
+// After close to a million updates the hash must still be the one of a VM
+// rebuilt from the final state, and stay so through changes from outside.
+#[test]
+fn incremental_state_hash_matches_a_fresh_one()
+{
+    let mut vm = replay(Backend::BlockJit).vm;
+    assert_eq!(vm.state_hash(), rebuild(&vm).state_hash());
+
+    let mut memory = vm.memory().clone();
+    memory.write(200, 9);
+    assert_eq!(memory.state_hash(), Memory::new(memory.to_vec()).state_hash());
+
+    vm.write_memory(100, 7).unwrap();
+    assert_eq!(vm.state_hash(), rebuild(&vm).state_hash());
+    vm.patch_memory(6000, 42).unwrap();
+    assert_eq!(vm.state_hash(), rebuild(&vm).state_hash());
+    vm.set_register(3, 12345).unwrap();
+    assert_eq!(vm.state_hash(), rebuild(&vm).state_hash());
+    vm.push_stack(77);
+    assert_eq!(vm.state_hash(), rebuild(&vm).state_hash());
+    vm.set_program_counter(1234);
+    assert_eq!(vm.state_hash(), rebuild(&vm).state_hash());
+}
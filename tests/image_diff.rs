@@ -0,0 +1,43 @@
+extern crate synacor_challenge;
+
+use std::env;
+use synacor_challenge::image_diff::{ChangedRange, diff, describe};
+use synacor_challenge::loader::{convert_to_bytes_le, convert_to_u16_le, read_image_file, write_image_file};
+
+#[test]
+fn export_round_trips_through_the_loader()
+{
+    let words = vec!(0, 1, 0x1234, 32775, 65535);
+    assert_eq!(convert_to_bytes_le(&words)[4..6], [0x34, 0x12]);
+    assert_eq!(convert_to_u16_le(&convert_to_bytes_le(&words)).unwrap(), words);
+
+    let mut path = env::temp_dir();
+    path.push(format!("synacor_export_{}.bin", std::process::id()));
+    let file_name = path.to_str().unwrap();
+    write_image_file(file_name, &words).unwrap();
+    assert_eq!(read_image_file(file_name).unwrap(), words);
+}
+
+#[test]
+fn changed_words_are_grouped_into_ranges()
+{
+    let before = [1, 2, 3, 4, 5, 6];
+    let after = [1, 9, 9, 4, 5, 7, 72, 105];
+    let ranges = diff(&before, &after);
+    assert_eq!
+    (
+        ranges,
+        [
+            ChangedRange { start : 1, before : vec!(Some(2), Some(3)), after : vec!(Some(9), Some(9)) },
+            ChangedRange { start : 5, before : vec!(Some(6), None, None), after : vec!(Some(7), Some(72), Some(105)) },
+        ]
+    );
+    assert_eq!
+    (
+        describe(&ranges),
+        "5 words changed in 2 ranges\n\
+         1..3: 2 words\n  - 0002 0003\n  + 0009 0009\n    ..\n\
+         5..8: 3 words\n  - 0006 ---- ----\n  + 0007 0048 0069\n    .Hi\n"
+    );
+    assert!(diff(&before, &before).is_empty());
+}
@@ -0,0 +1,104 @@
+extern crate synacor_challenge;
+
+mod common;
+
+use common::R0;
+use synacor_challenge::code_watch::{CodeWatch, Modification};
+use synacor_challenge::memory::Memory;
+use synacor_challenge::memory_view::{MemoryView, CommandFailure, Edit, Encoding, encode, search};
+use synacor_challenge::run_until::StopConditions;
+use synacor_challenge::vm::RunFailure;
+
+// 0: out 'A'; 2: halt; 3: "Hi" length prefixed; 6: "Hi" raw
+const PROGRAM : [u16; 8] = [19, 65, 0, 2, 72, 105, 72, 105];
+
+#[test]
+fn search_values_and_strings()
+{
+    let memory = Memory::new(PROGRAM.to_vec());
+    assert_eq!(encode("Hi", Encoding::LengthPrefixed), [2, 72, 105]);
+    assert_eq!(search(&memory, &[72]), [4, 6]);
+    assert_eq!(search(&memory, &encode("Hi", Encoding::Raw)), [4, 6]);
+    assert_eq!(search(&memory, &encode("Hi", Encoding::LengthPrefixed)), [3]);
+
+    let mut vm = common::vm(&PROGRAM);
+    let mut view = MemoryView::new();
+    assert_eq!(view.execute(&mut vm, "find str Hi").unwrap(), "1 matches: 3\n");
+    assert_eq!(view.address, 3);
+    assert_eq!(view.execute(&mut vm, "find 0x48").unwrap(), "2 matches: 4 6\n");
+}
+
+#[test]
+fn formats_and_jumps()
+{
+    let mut vm = common::vm(&PROGRAM);
+    let mut view = MemoryView::new();
+    assert_eq!(view.execute(&mut vm, "").unwrap(), "    0  0013 0041 0000 0002 0048 0069 0048 0069  .A..HiHi\n");
+    assert_eq!(view.execute(&mut vm, "dec").unwrap(), "    0     19    65     0     2    72   105    72   105  .A..HiHi\n");
+    assert_eq!(view.execute(&mut vm, "ascii").unwrap(), "    0   .  'A'  .   .  'H' 'i' 'H' 'i'  .A..HiHi\n");
+    assert_eq!(view.execute(&mut vm, "goto 4").unwrap(), "    4  'H' 'i' 'H' 'i'  HiHi\n");
+    assert_eq!(view.execute(&mut vm, "6").unwrap(), "    6  'H' 'i'  Hi\n");
+    assert!(matches!(view.execute(&mut vm, "bogus"), Err(CommandFailure::Usage(_))));
+    assert!(matches!(view.execute(&mut vm, "goto x"), Err(CommandFailure::InvalidNumber(_))));
+}
+
+#[test]
+fn patches_are_logged_and_reach_the_program()
+{
+    let mut vm = common::vm(&PROGRAM);
+    let mut view = MemoryView::new();
+    // Run once so the instructions are in the decode cache before patching them.
+    vm.run(&StopConditions::new());
+    assert_eq!(vm.take_output(), "A");
+    assert_eq!(view.execute(&mut vm, "set 1 66 33").unwrap(), "1: 65 -> 66\n2: 0 -> 33\n");
+    view.execute(&mut vm, "set 2 0").unwrap();
+    assert_eq!
+    (
+        view.edits(),
+        [
+            Edit { step : 2, address : 1, old : 65, new : 66 },
+            Edit { step : 2, address : 2, old : 0, new : 33 },
+            Edit { step : 2, address : 2, old : 33, new : 0 },
+        ]
+    );
+    assert_eq!(view.execute(&mut vm, "edits").unwrap(), "step 2: 1: 65 -> 66\nstep 2: 2: 0 -> 33\nstep 2: 2: 33 -> 0\n");
+
+    vm.set_program_counter(0);
+    vm.run(&StopConditions::new());
+    assert_eq!(vm.take_output(), "B");
+
+    // Writes outside of the image are refused like a `wmem` would be.
+    assert!(matches!(view.execute(&mut vm, "set 100 1"), Err(CommandFailure::WriteFailed(RunFailure::InvalidAddress { .. }))));
+    assert_eq!(view.edits().len(), 3);
+}
+
+#[test]
+fn patches_to_decoded_code_reach_the_code_watch()
+{
+    let mut vm = common::vm(&PROGRAM);
+    vm.set_code_watch(CodeWatch::new());
+    let mut view = MemoryView::new();
+    vm.run(&StopConditions::new());
+    // The string at 4 is data, only the `out` operand is code.
+    view.execute(&mut vm, "set 1 66").unwrap();
+    view.execute(&mut vm, "set 4 73").unwrap();
+    let program_counter = vm.program_counter();
+    assert_eq!
+    (
+        vm.code_watch().unwrap().modifications(),
+        [
+            Modification { step : 2, program_counter, address : 1, old : 65, new : 66 },
+        ]
+    );
+}
+
+#[test]
+fn commands_are_told_apart_by_their_first_word()
+{
+    // in r0; halt
+    let mut vm = common::vm(&[20, R0, 0]);
+    vm.push_input_line("mem find str dump");
+    vm.push_input_line("x");
+    vm.run(&StopConditions::new());
+    assert_eq!(vm.take_output(), "0 matches: \n");
+}
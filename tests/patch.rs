@@ -0,0 +1,68 @@
+extern crate synacor_challenge;
+
+mod common;
+
+use synacor_challenge::patch::{Patch, PatchWord, PatchFailure, image_checksum, apply_at_load, run_patched};
+use synacor_challenge::run_until::StopReason;
+
+// out 'A'; out 'A'; out 'A'; halt
+const PROGRAM : [u16; 7] = [19, 65, 19, 65, 19, 65, 0];
+
+fn patch(step : Option<u64>, words : &[(u16, u16, u16)]) -> Patch
+{
+    Patch
+    {
+        image_checksum : image_checksum(&PROGRAM),
+        step,
+        words : words.iter().map(|&(address, old, new)| PatchWord { address, old, new }).collect(),
+    }
+}
+
+#[test]
+fn parse_and_print()
+{
+    let text = format!("# bypass\nimage {:016x}\nstep 2\n5 65 0x42 # 'B'\n", image_checksum(&PROGRAM));
+    let parsed = Patch::parse(&text).unwrap();
+    assert_eq!(parsed, patch(Some(2), &[(5, 65, 66)]));
+    assert_eq!(Patch::parse(&parsed.to_text()).unwrap(), parsed);
+
+    assert!(matches!(Patch::parse("5 65 66\n"), Err(PatchFailure::MissingImageChecksum)));
+    assert!(matches!(Patch::parse("image 12\n5 65\n"), Err(PatchFailure::InvalidLine(2, _))));
+    assert!(matches!(Patch::parse("image xyz\n"), Err(PatchFailure::InvalidLine(1, _))));
+}
+
+#[test]
+fn patches_at_load_and_at_a_step()
+{
+    let mut vm = common::vm(&PROGRAM);
+    let patches = vec!(patch(Some(2), &[(5, 65, 67)]), patch(None, &[(1, 65, 66)]), patch(Some(1), &[(3, 65, 68)]));
+    let scheduled = apply_at_load(&mut vm, &PROGRAM, patches).unwrap();
+    assert_eq!(scheduled.iter().map(|patch| patch.step).collect::<Vec<_>>(), [Some(1), Some(2)]);
+    assert_eq!(vm.memory().get(1), Some(66));
+
+    assert!(matches!(run_patched(&mut vm, &scheduled), Ok(StopReason::Halted)));
+    assert_eq!(vm.take_output(), "BDC");
+}
+
+#[test]
+fn mismatches_are_refused()
+{
+    let mut vm = common::vm(&PROGRAM);
+    let mut other_image = PROGRAM.to_vec();
+    other_image[6] = 21;
+    assert!
+    (
+        matches!
+        (
+            apply_at_load(&mut vm, &other_image, vec!(patch(None, &[(1, 65, 66)]))),
+            Err(PatchFailure::ImageMismatch { .. })
+        )
+    );
+
+    // Nothing is written when one old word does not match.
+    let result = patch(None, &[(1, 65, 66), (3, 66, 67)]).apply(&mut vm);
+    assert!(matches!(result, Err(PatchFailure::OldWordMismatch { address : 3, expected : 66, actual : Some(65) })));
+    let result = patch(None, &[(100, 0, 1)]).apply(&mut vm);
+    assert!(matches!(result, Err(PatchFailure::OldWordMismatch { address : 100, expected : 0, actual : None })));
+    assert_eq!(vm.memory().to_vec(), PROGRAM);
+}
@@ -0,0 +1,46 @@
+extern crate synacor_challenge;
+
+mod common;
+
+use common::adventure::{start, Room};
+use synacor_challenge::explorer::Explorer;
+use synacor_challenge::planner::{plan, plan_from, PlanFailure, Target};
+use synacor_challenge::run_until::{StopConditions, StopReason};
+
+// Hall -> Cellar -> Vault, and a Garden off the hall. The vault and the
+// garden both hold a key, the garden is closer.
+const HOUSE : [Room; 4] =
+    [
+        Room { name : "Hall", description : "A hall.", items : &[], exits : &[("down", 1), ("west", 3)] },
+        Room { name : "Cellar", description : "A cellar.", items : &["lamp"], exits : &[("up", 0), ("north", 2)] },
+        Room { name : "Vault", description : "A vault.", items : &["key"], exits : &[("south", 1)] },
+        Room { name : "Garden", description : "A garden.", items : &["key"], exits : &[("east", 0)] },
+    ];
+
+#[test]
+fn plans_walk_the_shortest_way()
+{
+    let graph = Explorer::new().explore(&start(&HOUSE)).unwrap();
+    // Room ids follow the order the search found them in.
+    let names : Vec<&str> = graph.rooms.iter().map(|room| room.name.as_str()).collect();
+    assert_eq!(names, ["Hall", "Cellar", "Garden", "Vault"]);
+    assert_eq!(plan_from(&graph, 0, &Target::Room("vault".to_owned())).unwrap(), ["down", "north"]);
+    assert_eq!(plan_from(&graph, 3, &Target::RoomId(2)).unwrap(), ["south", "up", "west"]);
+    assert_eq!(plan_from(&graph, 0, &Target::Item("lamp".to_owned())).unwrap(), ["down", "take lamp"]);
+    // The nearest of the rooms holding the item.
+    assert_eq!(plan_from(&graph, 0, &Target::Item("key".to_owned())).unwrap(), ["west", "take key"]);
+    assert!(matches!(plan_from(&graph, 0, &Target::Item("sword".to_owned())), Err(PlanFailure::UnknownTarget)));
+}
+
+#[test]
+fn plans_start_in_the_current_room()
+{
+    let mut vm = start(&HOUSE);
+    let graph = Explorer::new().explore(&vm).unwrap();
+    vm.push_input_line("down");
+    vm.push_input_line("north");
+    assert!(matches!(vm.run(&StopConditions::new()), StopReason::InputNeeded));
+
+    assert_eq!(plan(&graph, &vm, &Target::Room("Hall".to_owned())).unwrap(), ["south", "up"]);
+    assert_eq!(plan(&graph, &vm, &Target::Item("key".to_owned())).unwrap(), ["take key"]);
+}
@@ -0,0 +1,114 @@
+extern crate synacor_challenge;
+
+mod common;
+
+use common::R0;
+use synacor_challenge::jit::Backend;
+use synacor_challenge::run_until::{StopConditions, StopReason};
+use synacor_challenge::vm::{VM, RunFailure};
+
+// Runs `program` under both backends until `conditions` stop it.
+fn run<F>(program : &[u16], conditions : F) -> Vec<(VM, StopReason)>
+    where F : Fn() -> StopConditions
+{
+    let mut runs = vec!();
+    for &backend in &[Backend::Interpreter, Backend::BlockJit]
+    {
+        let mut vm = common::vm(program);
+        vm.set_backend(backend);
+        let reason = vm.run(&conditions());
+        runs.push((vm, reason));
+    }
+    runs
+}
+
+// 0: add r0 r0 1; 4: jmp 0
+const COUNT : [u16; 6] = [9, R0, R0, 1, 6, 0];
+
+#[test]
+fn step_budget()
+{
+    for (vm, reason) in run(&COUNT, || StopConditions::new().max_steps(7))
+    {
+        assert!(matches!(reason, StopReason::StepBudgetExhausted), "unexpected stop {:?}", reason);
+        assert_eq!(vm.step_nb(), 7);
+        assert_eq!(vm.registers()[0], 4);
+    }
+}
+
+#[test]
+fn breakpoint()
+{
+    for (vm, reason) in run(&COUNT, || StopConditions::new().stop_at(4).max_steps(100))
+    {
+        assert!(matches!(reason, StopReason::PcReached(4)), "unexpected stop {:?}", reason);
+        assert_eq!(vm.step_nb(), 1);
+    }
+}
+
+#[test]
+fn halt()
+{
+    // add r0 r0 1; halt
+    for (vm, reason) in run(&[9, R0, R0, 1, 0], StopConditions::new)
+    {
+        assert!(matches!(reason, StopReason::Halted), "unexpected stop {:?}", reason);
+        assert_eq!(vm.registers()[0], 1);
+    }
+}
+
+// add r0 r0 1; in r0
+const READ : [u16; 6] = [9, R0, R0, 1, 20, R0];
+
+#[test]
+fn input_needed()
+{
+    for (vm, reason) in run(&READ, || StopConditions::new().stop_on_input())
+    {
+        assert!(matches!(reason, StopReason::InputNeeded), "unexpected stop {:?}", reason);
+        assert_eq!(vm.program_counter(), 4);
+    }
+    // Without asking for it the run still stops once the input queue is empty.
+    for (vm, reason) in run(&READ, StopConditions::new)
+    {
+        assert!(matches!(reason, StopReason::InputNeeded), "unexpected stop {:?}", reason);
+        assert_eq!(vm.program_counter(), 4);
+    }
+}
+
+#[test]
+fn output_matched()
+{
+    // out 'a'; out 'b'; out 'c'; halt
+    for (mut vm, reason) in run(&[19, 97, 19, 98, 19, 99, 0], || StopConditions::new().output_contains("ab"))
+    {
+        assert!(matches!(reason, StopReason::OutputMatched(ref pattern) if pattern == "ab"), "unexpected stop {:?}", reason);
+        assert_eq!(vm.take_output(), "ab");
+    }
+}
+
+#[test]
+fn predicate_matched()
+{
+    for (vm, reason) in run(&COUNT, || StopConditions::new().when(|vm| vm.registers()[0] == 5).max_steps(100))
+    {
+        assert!(matches!(reason, StopReason::PredicateMatched), "unexpected stop {:?}", reason);
+        assert_eq!(vm.registers()[0], 5);
+        assert_eq!(vm.program_counter(), 4);
+    }
+}
+
+#[test]
+fn fault()
+{
+    // add r0 r0 1; mod r0 r0 0
+    for (vm, reason) in run(&[9, R0, R0, 1, 11, R0, R0, 0], StopConditions::new)
+    {
+        match reason
+        {
+            StopReason::Fault(RunFailure::DivisionByZero { program_counter, .. }) => assert_eq!(program_counter, 4),
+            reason => panic!("unexpected stop {:?}", reason),
+        }
+        assert_eq!(vm.registers()[0], 1);
+    }
+}
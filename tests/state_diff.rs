@@ -0,0 +1,85 @@
+extern crate synacor_challenge;
+
+mod common;
+
+use std::env;
+use std::fs;
+use common::{run_with_input, R0, R1};
+use synacor_challenge::state_diff::{SavedState, RegisterChange, StackChange, LoadStateFailure, diff};
+
+// 0: push 7; 2: push 8; 4: in r0; 6: pop r1; 8: push r0; 10: wmem 20 r0; 13: wmem 21 r0; 16: halt
+const PROGRAM : [u16; 22] = [2, 7, 2, 8, 20, R0, 3, R1, 2, R0, 16, 20, R0, 16, 21, R0, 0, 0, 0, 0, 0, 0];
+
+#[test]
+fn diff_of_one_command()
+{
+    let waiting = run_with_input(&PROGRAM[..], &[]);
+    let done = run_with_input(&PROGRAM[..], &["a"]);
+    let before = SavedState::from_vm(&waiting.vm);
+    let after = SavedState::from_vm(&done.vm);
+
+    let changes = diff(&before, &after);
+    assert_eq!(changes.program_counter, (4, 16));
+    assert_eq!(changes.step, (2, 8));
+    assert_eq!
+    (
+        changes.registers,
+        [RegisterChange { register : 0, before : 0, after : 97 }, RegisterChange { register : 1, before : 0, after : 8 }]
+    );
+    assert_eq!(changes.stack, StackChange { kept : 1, removed : vec!(8), added : vec!(97) });
+    assert_eq!(changes.memory.len(), 1);
+    assert_eq!((changes.memory[0].start, changes.memory[0].end()), (20, 22));
+    assert!(!changes.is_empty());
+    assert!(diff(&after, &after).is_empty());
+
+    assert_eq!
+    (
+        changes.to_text(),
+        "pc 4 -> 16\nstep 2 -> 8 (+6)\nr0 0 -> 97\nr1 0 -> 8\n\
+         stack: 1 slots kept, removed [8], added [97]\n\
+         2 words changed in 1 ranges\n20..22: 2 words\n  - 0000 0000\n  + 0061 0061\n    aa\n"
+    );
+    let json = changes.to_json();
+    assert_eq!(json["step"]["delta"], 6);
+    assert_eq!(json["registers"][1]["after"], 8);
+    assert_eq!(json["stack"]["added"][0], 97);
+    assert_eq!(json["memory"][0]["after"][1], 97);
+}
+
+#[test]
+fn load_dump_directory()
+{
+    let mut dir = env::temp_dir();
+    dir.push(format!("synacor_state_{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let files =
+        [
+            ("registers.txt", "1\n2\n3\n4\n5\n6\n7\n8\n"),
+            ("stack.txt", "10\n11\n"),
+            ("program_counter.txt", "42\n"),
+            ("step_number.txt", "1000\n"),
+            ("memory.txt", "0\n21\n19\n"),
+        ];
+    for &(name, content) in &files
+    {
+        fs::write(dir.join(name), content).unwrap();
+    }
+    let dir = dir.to_str().unwrap();
+    let state = SavedState::load(dir).unwrap();
+    assert_eq!
+    (
+        state,
+        SavedState
+        {
+            registers : vec!(1, 2, 3, 4, 5, 6, 7, 8),
+            stack : vec!(10, 11),
+            program_counter : 42,
+            step : 1000,
+            memory : vec!(0, 21, 19),
+        }
+    );
+
+    fs::write(format!("{}/stack.txt", dir), "10\nx\n").unwrap();
+    assert!(matches!(SavedState::load(dir), Err(LoadStateFailure::InvalidNumber(_, 2))));
+    assert!(matches!(SavedState::load("/nonexistent"), Err(LoadStateFailure::CannotRead(_, _))));
+}
@@ -0,0 +1,131 @@
+extern crate chrono;
+extern crate synacor_challenge;
+
+mod common;
+
+use std::env;
+use std::fs;
+use chrono::NaiveDateTime;
+use common::R0;
+use synacor_challenge::jit::Backend;
+use synacor_challenge::run_until::{StopConditions, StopReason};
+use synacor_challenge::transcript;
+use synacor_challenge::transcript::{Record, Transcript};
+use synacor_challenge::vm::VM;
+
+// 0: out 'A'; 2: out '\n'; 4: in r0; 6: out r0; 8: in r0; 10: out r0
+// 12: in r0; 14: out r0; 16: out 'B'; 18: halt
+const PROGRAM : [u16; 19] = [19, 65, 19, 10, 20, R0, 19, R0, 20, R0, 19, R0, 20, R0, 19, R0, 19, 66, 0];
+
+fn time(text : &str) -> NaiveDateTime
+{
+    NaiveDateTime::parse_from_str(text, "%Y-%m-%dT%H:%M:%S%.3f").unwrap()
+}
+
+fn input(step : u64, line : &str) -> Record
+{
+    Record::Input { step, time : time("2026-10-19T10:00:00.000"), line : line.to_owned() }
+}
+
+fn output(step : u64, line : &str) -> Record
+{
+    Record::Output { step, time : time("2026-10-19T10:00:00.000"), line : line.to_owned() }
+}
+
+fn run(backend : Backend, transcript : Transcript) -> VM
+{
+    let mut vm = common::vm(&PROGRAM);
+    vm.set_backend(backend);
+    vm.set_transcript(transcript);
+    vm.push_input_line("hi");
+    assert!(matches!(vm.run(&StopConditions::new()), StopReason::Halted));
+    assert_eq!(vm.take_output(), "A\nhi\nB");
+    vm
+}
+
+#[test]
+fn input_and_output_lines_are_recorded_with_steps()
+{
+    for &backend in &[Backend::Interpreter, Backend::BlockJit]
+    {
+        let mut transcript = run(backend, Transcript::in_memory()).take_transcript().unwrap();
+        transcript.finish();
+        let records = transcript.records();
+        assert_eq!
+        (
+            transcript::first_difference
+            (
+                records,
+                &[output(0, "A"), input(2, "hi"), output(3, "hi"), output(8, "B")]
+            ),
+            None
+        );
+        assert!(records[0].time() > NaiveDateTime::default());
+        assert!(records.windows(2).all(|pair| pair[0].time() <= pair[1].time()));
+    }
+}
+
+#[test]
+fn transcript_file_reads_back_as_the_same_records()
+{
+    let mut path = env::temp_dir();
+    path.push(format!("synacor_transcript_{}.txt", std::process::id()));
+    let file_name = path.to_str().unwrap().to_owned();
+
+    let mut transcript = run(Backend::Interpreter, Transcript::new(&file_name)).take_transcript().unwrap();
+    transcript.finish();
+    let text = fs::read_to_string(&file_name).unwrap();
+    let loaded = transcript::load(&file_name).unwrap();
+    fs::remove_file(&file_name).unwrap();
+
+    assert!(text.starts_with("# transcript started "));
+    // Times included.
+    assert_eq!(loaded, transcript.records());
+    assert_eq!(transcript::input_script(&loaded), ["hi"]);
+}
+
+#[test]
+fn empty_lines_and_comments_are_parsed()
+{
+    let text = "# comment\n> 1 2026-10-19T10:00:00.000\n< 5 2026-10-19T10:00:04.250 \n> 6 2026-10-19T10:00:04.251 a b\n";
+    let records = transcript::parse(text).unwrap();
+    assert_eq!
+    (
+        records,
+        [
+            Record::Output { step : 1, time : time("2026-10-19T10:00:00.000"), line : String::new() },
+            Record::Input { step : 5, time : time("2026-10-19T10:00:04.250"), line : String::new() },
+            Record::Output { step : 6, time : time("2026-10-19T10:00:04.251"), line : "a b".to_owned() },
+        ]
+    );
+    assert!(transcript::parse("? 1 2026-10-19T10:00:00.000 x\n").is_err());
+    assert!(transcript::parse("> x 2026-10-19T10:00:00.000\n").is_err());
+    assert!(transcript::parse("> 1 x\n").is_err());
+}
+
+#[test]
+fn first_difference_compares_steps_and_lengths()
+{
+    let a = [output(1, "a"), input(4, "go")];
+    let b = [output(1, "a"), input(5, "go")];
+    assert_eq!(transcript::first_difference(&a, &a), None);
+    assert_eq!(transcript::describe_difference(&a, &a), "same 2 records\n");
+    assert_eq!(transcript::first_difference(&a, &b), Some((1, Some(a[1].clone()), Some(b[1].clone()))));
+    assert_eq!(transcript::describe_difference(&a, &b), "records differ at 1\n- < 4 2026-10-19T10:00:00.000 go\n+ < 5 2026-10-19T10:00:00.000 go\n");
+    assert_eq!(transcript::describe_difference(&a[..1], &a), "records differ at 1\n- end of transcript\n+ < 4 2026-10-19T10:00:00.000 go\n");
+    // Runs at different times are the same run.
+    let later = [output(1, "a"), Record::Input { step : 4, time : time("2026-10-20T08:00:00.000"), line : "go".to_owned() }];
+    assert_eq!(transcript::first_difference(&a, &later), None);
+}
+
+#[test]
+fn forks_do_not_record()
+{
+    let mut vm = common::vm(&PROGRAM);
+    vm.set_transcript(Transcript::in_memory());
+    let mut fork = vm.fork();
+    fork.push_input_line("hi");
+    assert!(matches!(fork.run(&StopConditions::new()), StopReason::Halted));
+    assert!(fork.transcript().is_none());
+    assert!(vm.transcript().unwrap().records().is_empty());
+}
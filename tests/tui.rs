@@ -0,0 +1,82 @@
+extern crate synacor_challenge;
+extern crate ratatui;
+
+mod common;
+
+use ratatui::Terminal;
+use ratatui::backend::TestBackend;
+use ratatui::crossterm::event::{KeyCode, KeyEvent};
+use synacor_challenge::tui::{Tui, State};
+use synacor_challenge::vm::VM;
+use common::R0;
+
+// 0: out 'H'; 2: out 'i'; 4: out 10; 6: in r0; 8: out r0; 10: jmp 6
+const ECHO_PROGRAM : [u16; 12] = [19, 72, 19, 105, 19, 10, 20, R0, 19, R0, 6, 6];
+
+fn screen(tui : &Tui) -> String
+{
+    let mut terminal = Terminal::new(TestBackend::new(120, 40)).unwrap();
+    terminal.draw(|frame| tui.draw(frame)).unwrap();
+    let buffer = terminal.backend().buffer();
+    let mut text = String::new();
+    for y in 0..buffer.area.height
+    {
+        for x in 0..buffer.area.width
+        {
+            text.push_str(buffer[(x, y)].symbol());
+        }
+        text.push('\n');
+    }
+    text
+}
+
+fn type_line(tui : &mut Tui, line : &str)
+{
+    for c in line.chars()
+    {
+        tui.handle_key(KeyEvent::from(KeyCode::Char(c)));
+    }
+    tui.handle_key(KeyEvent::from(KeyCode::Enter));
+}
+
+#[test]
+fn panes_show_output_and_machine_state()
+{
+    let mut tui = Tui::new(VM::new(ECHO_PROGRAM.to_vec()));
+    tui.tick();
+    assert_eq!(tui.state(), State::WaitingForInput);
+
+    let screen = screen(&tui);
+    assert!(screen.contains("Hi"));
+    assert!(screen.contains("waiting for input"));
+    assert!(screen.contains("r0     0"));
+    assert!(screen.contains(">     6  in r0"));
+    assert!(screen.contains("    4  out '\\n'"));
+    assert!(screen.contains("    0  0013 0048 0013 0069"));
+}
+
+#[test]
+fn snapshot_and_restore()
+{
+    let mut tui = Tui::new(VM::new(ECHO_PROGRAM.to_vec()));
+    tui.tick();
+    tui.handle_key(KeyEvent::from(KeyCode::F(2)));
+    let step = tui.vm().step_nb();
+
+    type_line(&mut tui, "ab");
+    tui.tick();
+    assert_eq!(tui.output(), "Hi\nab\nab\n");
+
+    tui.handle_key(KeyEvent::from(KeyCode::F(3)));
+    assert_eq!(tui.state(), State::Paused);
+    assert_eq!(tui.vm().step_nb(), step);
+    assert_eq!(tui.output(), "Hi\n");
+
+    // Stepping runs one instruction at a time.
+    type_line(&mut tui, "c");
+    tui.handle_key(KeyEvent::from(KeyCode::F(10)));
+    tui.handle_key(KeyEvent::from(KeyCode::F(10)));
+    assert_eq!(tui.vm().step_nb(), step + 2);
+    assert_eq!(tui.output(), "Hi\nc\nc");
+    assert_eq!(tui.state(), State::Paused);
+}